@@ -33,6 +33,34 @@ function! LSP#range_end_line() abort
     return getpos("'>")[1]
 endfunction
 
+" Start of range as [line, byte column], both 0-based.
+function! LSP#range_start() abort
+    if v:lnum
+        return [v:lnum - 1, 0]
+    endif
+
+    let [l:lnum, l:col] = getpos("'<")[1:2]
+    return [l:lnum - 1, visualmode() ==# 'v' ? l:col - 1 : 0]
+endfunction
+
+" End of range (exclusive) as [line, byte column], both 0-based.
+function! LSP#range_end() abort
+    if v:lnum
+        return [v:lnum - 1 + v:count, 0]
+    endif
+
+    let [l:lnum, l:col] = getpos("'>")[1:2]
+    if visualmode() !=# 'v'
+        return [l:lnum, 0]
+    endif
+
+    " Column after the last selected character, which may be multibyte.
+    let l:line = getline(l:lnum)
+    let l:col = min([l:col, len(l:line)])
+    let l:col += len(matchstr(l:line[l:col - 1 :], '^.')) - 1
+    return [l:lnum - 1, max([l:col, 0])]
+endfunction
+
 function! LSP#visible_line_start() abort
     return line('w0') - 1
 endfunction
@@ -36,34 +36,6 @@ endfunction
 " `echo` message without trigger |hit-enter|
 function! s:EchoEllipsis(message) abort
     echo s:Ellipsis(a:message)
-
-    if !has('nvim-0.3.2') || get(g:, 'LanguageClient_showVirtualText', 0) != 1
-        return
-    endif
-
-    if a:message == ""
-        call nvim_buf_clear_highlight(bufnr(''), 1000, 0, -1)
-        return
-    endif
-
-    let l:message = get(g:, 'LanguageClient_virtualTextPrefix', '> ') . a:message
-    let l:message = substitute(l:message, "\t", ' ', 'g')
-    let l:message = substitute(l:message, "\n", '', 'g')
-
-    let l:hlgroup = ""
-    if l:message =~ '\[Error\]'
-        let l:hlgroup = "ALEError"
-        let l:message = substitute(l:message, '[error\][0-9]\+\]', "", 'g')
-    elseif l:message =~ '\[Warning\]'
-        let l:hlgroup = "ALEWarning"
-        let l:message = substitute(l:message, '[warning\][0-9]\+\]', "", 'g')
-    elseif l:message =~ '\[Information\]'
-        let l:hlgroup = "ALEInfo"
-        let l:message = substitute(l:message, '[information\][0-9]\+\]', "", 'g')
-    endif
-
-    call nvim_buf_clear_highlight(bufnr(''), 1000, 0, -1)
-    call nvim_buf_set_virtual_text(bufnr(''), 1000, line('.')-1, [[l:message, l:hlgroup]], {})
 endfunction
 
 " `echomsg` message without trigger |hit-enter|
@@ -118,10 +90,11 @@ function! s:Debug(message) abort
 endfunction
 
 function! s:hasSnippetSupport() abort
-    if get(g:, 'LanguageClient_hasSnippetSupport', 1) !=# 1
-        return 0
-    endif
+    " Snippets are expanded by built-in engine when no snippet plugin is found.
+    return get(g:, 'LanguageClient_hasSnippetSupport', 1) ==# 1
+endfunction
 
+function! s:hasSnippetPlugin() abort
     " https://github.com/SirVer/ultisnips
     if exists('g:did_plugin_ultisnips')
         return 1
@@ -213,13 +186,429 @@ function! s:MatchDelete(ids) abort
     endfor
 endfunction
 
+" Delete matches added to window {winid}, unless they are gone with the window.
+function! s:MatchDeleteInWindow(winid, ids) abort
+    if win_id2win(a:winid) == 0
+        return
+    endif
+
+    for l:id in a:ids
+        call matchdelete(l:id, a:winid)
+    endfor
+endfunction
+
 " Batch version of nvim_buf_add_highlight
-function! s:AddHighlights(source, highlights) abort
+" Optional argument is buffer number, current buffer by default.
+function! s:AddHighlights(source, highlights, ...) abort
+    let l:bufnr = get(a:000, 0, 0)
     for hl in a:highlights
-        call nvim_buf_add_highlight(0, a:source, hl.group, hl.line, hl.character_start, hl.character_end)
+        call nvim_buf_add_highlight(l:bufnr, a:source, hl.group, hl.line, hl.character_start, hl.character_end)
+    endfor
+endfunction
+
+" Replace virtual texts within [line_start, line_end) of a buffer. Optional argument is buffer
+" number, current buffer by default.
+function! s:SetVirtualTexts(namespace, line_start, line_end, virtual_texts, ...) abort
+    let l:bufnr = get(a:000, 0, 0)
+    call nvim_buf_clear_highlight(l:bufnr, a:namespace, a:line_start, a:line_end)
+    for vt in a:virtual_texts
+        let l:text = substitute(vt.text, "\t", ' ', 'g')
+        call nvim_buf_set_virtual_text(l:bufnr, a:namespace, vt.line, [[l:text, vt.hl_group]], {})
+    endfor
+endfunction
+
+" Remove all virtual texts from buffers of given files.
+function! s:ClearVirtualTexts(namespace, filenames) abort
+    for l:filename in a:filenames
+        let l:bufnr = bufnr(l:filename)
+        if l:bufnr != -1
+            call nvim_buf_clear_highlight(l:bufnr, a:namespace, 0, -1)
+        endif
+    endfor
+endfunction
+
+" Get quickfix list (winid -1) or location list of window winid.
+function! s:GetList(winid, what) abort
+    return a:winid == -1 ? getqflist(a:what) : getloclist(a:winid, a:what)
+endfunction
+
+" Set quickfix list (winid -1) or location list of window winid.
+function! s:SetList(winid, action, what) abort
+    return a:winid == -1 ? setqflist([], a:action, a:what) : setloclist(a:winid, [], a:action, a:what)
+endfunction
+
+" Id of list titled {title} in quickfix list stack (winid -1) or location list stack of window
+" winid, 0 if there is none.
+function! s:FindList(winid, title) abort
+    let l:last = get(s:GetList(a:winid, {'nr': '$'}), 'nr', 0)
+    for l:nr in range(1, l:last)
+        let l:info = s:GetList(a:winid, {'nr': l:nr, 'id': 0, 'title': 0})
+        if get(l:info, 'title', '') ==# a:title
+            return l:info.id
+        endif
+    endfor
+    return 0
+endfunction
+
+" Set diagnostics to quickfix list (winid -1) or location list of window winid.
+"
+" The current list is replaced only if it is empty or our own diagnostics list. Otherwise an
+" existing diagnostics list in the stack is updated, or a new one added to the end, without
+" making it current, so that lists created by others (e.g., grep results) stay current.
+function! s:SetDiagnosticsList(winid, list, title) abort
+    let l:what = {'title': a:title, 'items': a:list}
+    let l:current = s:GetList(a:winid, {'nr': 0, 'title': 0, 'size': 0})
+    if get(l:current, 'size', 0) == 0 || get(l:current, 'title', '') ==# a:title
+        return s:SetList(a:winid, 'r', l:what)
+    endif
+
+    let l:id = s:FindList(a:winid, a:title)
+    if l:id != 0
+        return s:SetList(a:winid, 'r', extend(l:what, {'id': l:id}))
+    endif
+
+    " Adding a list makes it current, go back to the previous one. Oldest list is dropped when
+    " stack is full.
+    let l:last = get(s:GetList(a:winid, {'nr': '$'}), 'nr', 0)
+    call s:SetList(a:winid, ' ', extend(l:what, {'nr': '$'}))
+    let l:new = get(s:GetList(a:winid, {'nr': '$'}), 'nr', 0)
+    let l:count = l:new - (l:new == l:last ? l:current.nr - 1 : l:current.nr)
+    if l:count > 0
+        let l:command = 'silent! ' . l:count . (a:winid == -1 ? 'colder' : 'lolder')
+        if a:winid == -1
+            execute l:command
+        else
+            call s:ExecuteInWindow(a:winid == 0 ? win_getid() : a:winid, [l:command])
+        endif
+    endif
+endfunction
+
+" Empty diagnostics lists in quickfix list stack and in location list stacks of all windows,
+" e.g., after switching to another kind of list.
+function! s:ClearDiagnosticsLists(title) abort
+    for l:winid in [-1] + map(getwininfo(), 'v:val.winid')
+        let l:id = s:FindList(l:winid, a:title)
+        if l:id != 0
+            call s:SetList(l:winid, 'r', {'id': l:id, 'items': []})
+        endif
+    endfor
+endfunction
+
+" Set diagnostics summary of all files and of buffers of given files.
+function! s:SetDiagnosticsSummary(buffers, total) abort
+    let g:LanguageClient_diagnosticsSummary = a:total
+    for [l:filename, l:summary] in items(a:buffers)
+        let l:bufnr = bufnr(l:filename)
+        if l:bufnr != -1
+            call setbufvar(l:bufnr, 'LanguageClient_diagnosticsSummary', l:summary)
+        endif
+    endfor
+endfunction
+
+" Remember diagnostics list of a buffer and set it to every window displaying the buffer.
+function! s:SetBufferDiagnosticsList(filename, list, title) abort
+    let l:bufnr = bufnr(a:filename)
+    if l:bufnr == -1
+        return
+    endif
+
+    call setbufvar(l:bufnr, 'LanguageClient_diagnosticsList', {'title': a:title, 'items': a:list})
+    for l:winid in win_findbuf(l:bufnr)
+        call s:SetDiagnosticsList(l:winid, a:list, a:title)
+    endfor
+endfunction
+
+function! s:ClearBufferDiagnosticsLists() abort
+    for l:bufnr in range(1, bufnr('$'))
+        if bufexists(l:bufnr)
+            call setbufvar(l:bufnr, 'LanguageClient_diagnosticsList', {})
+        endif
+    endfor
+endfunction
+
+" Start snippet session, given tabstops in jump order, each a list of [lnum, col, len] of its
+" occurrences, and choices of each tabstop.
+function! s:StartSnippet(tabstops, choices) abort
+    let b:LanguageClient_snippet = {
+                \ 'tabstops': a:tabstops,
+                \ 'choices': a:choices,
+                \ 'index': -1,
+                \ }
+    call LanguageClient#snippetNext()
+endfunction
+
+" Shift tabstops after current one by text typed into it since last jump.
+function! s:UpdateSnippetTabstops(snippet) abort
+    if a:snippet.index < 0
+        return
+    endif
+
+    let [l:lnum, l:col, l:len] = a:snippet.tabstops[a:snippet.index][0]
+    let l:line_delta = line('$') - a:snippet.line_count
+    let l:col_delta = len(getline(l:lnum + l:line_delta)) - a:snippet.line_len
+    for l:ranges in a:snippet.tabstops[a:snippet.index + 1 :]
+        for l:range in l:ranges
+            if l:range[0] > l:lnum
+                let l:range[0] += l:line_delta
+            elseif l:range[0] == l:lnum && l:range[1] >= l:col + l:len
+                let l:range[0] += l:line_delta
+                let l:range[1] += l:col_delta
+            endif
+        endfor
     endfor
 endfunction
 
+function! s:JumpSnippet(delta) abort
+    let l:snippet = get(b:, 'LanguageClient_snippet', {})
+    if empty(l:snippet)
+        return ''
+    endif
+
+    call s:UpdateSnippetTabstops(l:snippet)
+    let l:index = l:snippet.index + a:delta
+    if l:index < 0 || l:index >= len(l:snippet.tabstops)
+        unlet b:LanguageClient_snippet
+        return ''
+    endif
+
+    let l:snippet.index = l:index
+    let [l:lnum, l:col, l:len] = l:snippet.tabstops[l:index][0]
+    let l:snippet.line_count = line('$')
+    let l:snippet.line_len = len(getline(l:lnum))
+    if l:index == len(l:snippet.tabstops) - 1
+        " Final tabstop ends snippet.
+        unlet b:LanguageClient_snippet
+    endif
+
+    let l:keys = "\<C-\>\<C-n>:\<C-u>call cursor(" . l:lnum . ', ' . l:col . ")\<CR>"
+    if l:len == 0
+        let l:keys .= l:col > len(getline(l:lnum)) ? 'a' : 'i'
+    else
+        " Select placeholder, so that typing replaces it.
+        let l:chars = strchars(getline(l:lnum)[l:col - 1 : l:col + l:len - 2])
+        let l:keys .= 'v' . (l:chars > 1 ? (l:chars - 1) . 'l' : '')
+        if !empty(get(l:snippet.choices, l:index, []))
+            let l:keys .= "c\<C-r>=LanguageClient#snippetChoices()\<CR>"
+        else
+            let l:keys .= "\<C-g>"
+        endif
+    endif
+    call feedkeys(l:keys, 'n')
+    return ''
+endfunction
+
+" Show choices of current snippet tabstop in popup menu.
+function! LanguageClient#snippetChoices() abort
+    let l:snippet = get(b:, 'LanguageClient_snippet', {})
+    if !empty(l:snippet)
+        call complete(col('.'), l:snippet.choices[l:snippet.index])
+    endif
+    return ''
+endfunction
+
+let s:floatingHoverWinid = -1
+" Position of popup menu, and floating window showing documentation next to it, in neovim.
+let s:completionMenu = {}
+let s:completionDocumentationWinid = -1
+
+" Show lines in a floating window (neovim) or popup (vim) next to cursor, sized to content.
+function! s:OpenHoverPreview(bufname, lines, filetype, regions) abort
+    call LanguageClient#closeFloatingHover()
+
+    " In insert mode, e.g., signature help while typing arguments, keep window open when cursor
+    " moves, until insert mode is left or it is replaced with a newer one.
+    let l:insert = mode(1) =~# '^i' || mode(1) ==# 'niI'
+    if exists('*popup_atcursor')
+        let s:floatingHoverWinid = popup_atcursor(a:lines, {
+                    \ 'moved': l:insert ? [0, 0, 0] : 'any',
+                    \ 'padding': [0, 1, 0, 1],
+                    \ 'maxwidth': &columns / 2,
+                    \ 'maxheight': &lines / 2,
+                    \ 'scrollbar': 1,
+                    \ })
+        if a:filetype !=# ''
+            call setbufvar(winbufnr(s:floatingHoverWinid), '&filetype', a:filetype)
+        endif
+        call s:HighlightRegions(s:floatingHoverWinid, a:regions)
+        if l:insert
+            augroup LanguageClientFloatingHover
+                autocmd!
+                autocmd InsertLeave <buffer> call s:CloseFloatingHoverOnInsertLeave()
+            augroup END
+        endif
+        return
+    endif
+
+    let [l:width, l:height] = s:FloatingWindowSize(a:lines, &columns / 2, &lines / 2)
+    " Above cursor if there is room, below otherwise.
+    let l:above = winline() > l:height + 1
+    let s:floatingHoverWinid = s:OpenFloatingWindow(a:lines, a:filetype, {
+                \ 'relative': 'cursor',
+                \ 'anchor': l:above ? 'SW' : 'NW',
+                \ 'row': l:above ? 0 : 1,
+                \ 'col': 0,
+                \ 'width': l:width,
+                \ 'height': l:height,
+                \ })
+    call s:HighlightRegions(s:floatingHoverWinid, a:regions)
+
+    augroup LanguageClientFloatingHover
+        autocmd!
+        if l:insert
+            autocmd InsertLeave <buffer> call s:CloseFloatingHoverOnInsertLeave()
+        else
+            autocmd CursorMoved,CursorMovedI,InsertEnter <buffer> call LanguageClient#closeFloatingHover()
+        endif
+    augroup END
+    call nvim_buf_set_keymap(winbufnr(s:floatingHoverWinid), 'n', 'q', ':close<CR>',
+                \ {'silent': v:true, 'nowait': v:true})
+endfunction
+
+" Size of floating window showing lines, with room for padding and wrapped lines.
+function! s:FloatingWindowSize(lines, maxwidth, maxheight) abort
+    let l:width = max(map(copy(a:lines), 'strdisplaywidth(v:val)')) + 2
+    let l:width = max([min([l:width, a:maxwidth]), 1])
+    let l:height = 0
+    for l:line in a:lines
+        let l:height += max([1, float2nr(ceil(strdisplaywidth(l:line) / (l:width - 2.0)))])
+    endfor
+    let l:height = max([min([l:height, a:maxheight]), 1])
+    return [l:width, l:height]
+endfunction
+
+" Open neovim floating window showing lines in a scratch buffer, placed as in config.
+function! s:OpenFloatingWindow(lines, filetype, config) abort
+    let l:buf = nvim_create_buf(v:false, v:true)
+    call nvim_buf_set_lines(l:buf, 0, -1, v:false, a:lines)
+    call nvim_buf_set_option(l:buf, 'bufhidden', 'wipe')
+    if a:filetype !=# ''
+        call nvim_buf_set_option(l:buf, 'filetype', a:filetype)
+    endif
+    let l:winid = nvim_open_win(l:buf, v:false, extend({'style': 'minimal'}, a:config))
+    call nvim_win_set_option(l:winid, 'wrap', v:true)
+    call nvim_win_set_option(l:winid, 'foldcolumn', 1)
+    return l:winid
+endfunction
+
+" Highlight lines of window with syntax of other filetypes, e.g., code blocks in markdown.
+function! s:HighlightRegions(winid, regions) abort
+    let l:commands = ['silent! syntax clear LanguageClientCodeBlock']
+    for l:region in a:regions
+        let l:cluster = '@LanguageClientCode_' . substitute(l:region.filetype, '\W', '_', 'g')
+        " Syntax files are skipped when b:current_syntax is set.
+        call add(l:commands, 'let s:current_syntax = get(b:, "current_syntax", v:null)')
+        call add(l:commands, 'unlet! b:current_syntax')
+        call add(l:commands, printf('silent! syntax include %s syntax/%s.vim', l:cluster, fnameescape(l:region.filetype)))
+        call add(l:commands, 'if s:current_syntax isnot v:null | let b:current_syntax = s:current_syntax | endif')
+        call add(l:commands, printf('syntax region LanguageClientCodeBlock start=/\%%%dl/ end=/\%%%dl$/ keepend contains=%s',
+                    \ l:region.start + 1, l:region.end + 1, l:cluster))
+    endfor
+
+    call s:ExecuteInWindow(a:winid, l:commands)
+endfunction
+
+" Highlight part of a line in floating window, e.g., active parameter of signature.
+function! s:HighlightFloatingHover(group, line, col, length) abort
+    if s:floatingHoverWinid != -1
+        call s:ExecuteInWindow(s:floatingHoverWinid, [printf('call matchaddpos(%s, [[%d, %d, %d]])',
+                    \ string(a:group), a:line, a:col, a:length)])
+    endif
+endfunction
+
+function! s:ExecuteInWindow(winid, commands) abort
+    if exists('*win_execute')
+        call win_execute(a:winid, a:commands, 'silent!')
+    elseif win_id2win(a:winid) > 0
+        let l:winid = win_getid()
+        noautocmd call win_gotoid(a:winid)
+        for l:command in a:commands
+            execute l:command
+        endfor
+        noautocmd call win_gotoid(l:winid)
+    endif
+endfunction
+
+" Close floating window opened for hover, signature help or error explanation.
+function! LanguageClient#closeFloatingHover() abort
+    augroup LanguageClientFloatingHover
+        autocmd!
+    augroup END
+    if s:floatingHoverWinid == -1
+        return
+    elseif exists('*popup_close')
+        call popup_close(s:floatingHoverWinid)
+    elseif nvim_win_is_valid(s:floatingHoverWinid)
+        call nvim_win_close(s:floatingHoverWinid, v:true)
+    endif
+    let s:floatingHoverWinid = -1
+endfunction
+
+function! s:CloseFloatingHoverOnInsertLeave() abort
+    " Keep it open while running a single command with i_CTRL-O, e.g., to cycle signatures.
+    if mode(1) !=# 'niI'
+        call LanguageClient#closeFloatingHover()
+    endif
+endfunction
+
+" Focus floating window, e.g., to scroll it. Returns whether there was one.
+function! LanguageClient#focusFloatingHover() abort
+    if !has('nvim') || s:floatingHoverWinid == -1 || !nvim_win_is_valid(s:floatingHoverWinid)
+        return 0
+    endif
+
+    " Keep it open when cursor moves in it.
+    augroup LanguageClientFloatingHover
+        autocmd!
+    augroup END
+    call win_gotoid(s:floatingHoverWinid)
+    return 1
+endfunction
+
+" Show documentation of completion item in info popup, or in floating window next to popup menu
+" in neovim, if item is still selected.
+function! s:ShowCompletionItemDocumentation(selected, lines) abort
+    if !pumvisible() || complete_info(['selected']).selected != a:selected
+        return
+    endif
+
+    if exists('*popup_findinfo')
+        let l:winid = popup_findinfo()
+        if l:winid
+            call popup_settext(l:winid, a:lines)
+            call popup_show(l:winid)
+        endif
+        return
+    elseif !has('nvim') || empty(s:completionMenu)
+        return
+    endif
+
+    call s:CloseCompletionItemDocumentation()
+    " On the side of popup menu with more room.
+    let l:menu = s:completionMenu
+    let l:right = l:menu.col + l:menu.width + (l:menu.scrollbar ? 1 : 0)
+    let l:room = max([&columns - l:right, l:menu.col])
+    if l:room < 3
+        return
+    endif
+    let [l:width, l:height] = s:FloatingWindowSize(a:lines, min([l:room, &columns / 2]),
+                \ max([&lines - &cmdheight - l:menu.row, 1]))
+    let s:completionDocumentationWinid = s:OpenFloatingWindow(a:lines, '', {
+                \ 'relative': 'editor',
+                \ 'row': l:menu.row,
+                \ 'col': &columns - l:right >= l:menu.col ? l:right : l:menu.col - l:width,
+                \ 'width': l:width,
+                \ 'height': l:height,
+                \ 'focusable': v:false,
+                \ })
+endfunction
+
+function! s:CloseCompletionItemDocumentation() abort
+    if s:completionDocumentationWinid != -1 && nvim_win_is_valid(s:completionDocumentationWinid)
+        call nvim_win_close(s:completionDocumentationWinid, v:true)
+    endif
+    let s:completionDocumentationWinid = -1
+endfunction
+
 " Get an variable value.
 " First try buffer local, then global, then default, then v:null.
 function! s:GetVar(...) abort
@@ -508,6 +897,11 @@ function! LanguageClient#Notify(method, params) abort
 endfunction
 
 function! LanguageClient#textDocument_hover(...) abort
+    " Calling hover again focuses floating window already opened.
+    if has('nvim') && a:0 == 0 && LanguageClient#focusFloatingHover()
+        return
+    endif
+
     let l:Callback = get(a:000, 1, v:null)
     let l:params = {
                 \ 'filename': LSP#filename(),
@@ -624,6 +1018,54 @@ function! LanguageClient#textDocument_codeAction(...) abort
     return LanguageClient#Call('textDocument/codeAction', l:params, l:Callback)
 endfunction
 
+function! LanguageClient#textDocument_visualCodeAction(...) abort
+    let l:Callback = get(a:000, 1, v:null)
+    let l:params = {
+                \ 'filename': LSP#filename(),
+                \ 'text': LSP#text(),
+                \ 'line': LSP#line(),
+                \ 'character': LSP#character(),
+                \ 'LSP#range_start_line()': LSP#range_start_line(),
+                \ 'LSP#range_end_line()': LSP#range_end_line(),
+                \ 'handle': s:IsFalse(l:Callback),
+                \ }
+    call extend(l:params, get(a:000, 0, {}))
+    return LanguageClient#Call('textDocument/codeAction', l:params, l:Callback)
+endfunction
+
+function! LanguageClient#quickFix(...) abort
+    let l:Callback = get(a:000, 1, v:null)
+    let l:params = {
+                \ 'filename': LSP#filename(),
+                \ 'text': LSP#text(),
+                \ 'line': LSP#line(),
+                \ 'character': LSP#character(),
+                \ 'handle': s:IsFalse(l:Callback),
+                \ }
+    call extend(l:params, get(a:000, 0, {}))
+    return LanguageClient#Call('languageClient/quickFix', l:params, l:Callback)
+endfunction
+
+function! LanguageClient#codeActionsOnSave(...) abort
+    let l:Callback = get(a:000, 1, v:null)
+    let l:params = {
+                \ 'filename': LSP#filename(),
+                \ 'handle': s:IsFalse(l:Callback),
+                \ }
+    call extend(l:params, get(a:000, 0, {}))
+    return LanguageClient#Call('languageClient/codeActionsOnSave', l:params, l:Callback)
+endfunction
+
+function! LanguageClient#formatOnSave(...) abort
+    let l:Callback = get(a:000, 1, v:null)
+    let l:params = {
+                \ 'filename': LSP#filename(),
+                \ 'handle': s:IsFalse(l:Callback),
+                \ }
+    call extend(l:params, get(a:000, 0, {}))
+    return LanguageClient#Call('languageClient/formatOnSave', l:params, l:Callback)
+endfunction
+
 function! LanguageClient#textDocument_completion(...) abort
     " Note: do not add 'text' as it might be huge.
     let l:params = {
@@ -657,14 +1099,28 @@ function! LanguageClient#textDocument_rangeFormatting(...) abort
                 \ 'text': LSP#text(),
                 \ 'line': LSP#line(),
                 \ 'character': LSP#character(),
-                \ 'LSP#range_start_line()': LSP#range_start_line(),
-                \ 'LSP#range_end_line()': LSP#range_end_line(),
+                \ 'LSP#range_start()': LSP#range_start(),
+                \ 'LSP#range_end()': LSP#range_end(),
                 \ 'handle': s:IsFalse(l:Callback),
                 \ }
     call extend(l:params, get(a:000, 0, {}))
     return LanguageClient#Call('textDocument/rangeFormatting', l:params, l:Callback)
 endfunction
 
+function! LanguageClient#textDocument_onTypeFormatting(ch, ...) abort
+    let l:Callback = get(a:000, 1, v:null)
+    let l:params = {
+                \ 'filename': LSP#filename(),
+                \ 'text': LSP#text(),
+                \ 'line': LSP#line(),
+                \ 'character': LSP#character(),
+                \ 'ch': a:ch,
+                \ 'handle': s:IsFalse(l:Callback),
+                \ }
+    call extend(l:params, get(a:000, 0, {}))
+    return LanguageClient#Call('textDocument/onTypeFormatting', l:params, l:Callback)
+endfunction
+
 function! LanguageClient#completionItem_resolve(completion_item, ...) abort
     let l:Callback = get(a:000, 1, v:null)
     let l:params = {
@@ -761,6 +1217,14 @@ function! LanguageClient#setDiagnosticsList(diagnosticsList) abort
     return LanguageClient#Call('languageClient/setDiagnosticsList', l:params, v:null)
 endfunction
 
+function! LanguageClient#toggleDiagnosticsFilter(filter, ...) abort
+    let l:params = {
+                \ 'filter': a:filter,
+                \ 'enable': a:0 > 0 ? a:1 : v:null,
+                \ }
+    return LanguageClient#Call('languageClient/toggleDiagnosticsFilter', l:params, v:null)
+endfunction
+
 function! LanguageClient#registerHandlers(handlers, ...) abort
     let l:handle = a:0 > 0 ? a:1 : v:null
     return LanguageClient#Call('languageClient/registerHandlers', a:handlers, l:handle)
@@ -803,16 +1267,56 @@ function! LanguageClient#handleFileType() abort
     endtry
 endfunction
 
+let s:typed = {'char': '', 'bufnr': -1, 'changedtick': -1}
+
+" Remember the character about to be inserted, so that only actual typing is reported as such.
+function! LanguageClient#handleInsertCharPre() abort
+    let s:typed = {'char': v:char, 'bufnr': bufnr('%'), 'changedtick': b:changedtick}
+endfunction
+
 function! LanguageClient#handleTextChanged() abort
     if &buftype !=# '' || &filetype ==# '' || expand('%') ==# ''
         return
     endif
 
     try
+        " Character just typed, in case it triggers on-type formatting. Deletions and other
+        " changes without a preceding InsertCharPre report nothing.
+        let l:typed = ''
+        if mode() ==# 'i' && s:typed.bufnr == bufnr('%') && b:changedtick > s:typed.changedtick
+            let l:typed = s:typed.char
+        endif
+        let s:typed = {'char': '', 'bufnr': -1, 'changedtick': -1}
+
         " Note: do not add 'text' as it might be huge.
         call LanguageClient#Notify('languageClient/handleTextChanged', {
                     \ 'filename': LSP#filename(),
+                    \ 'line': LSP#line(),
+                    \ 'character': LSP#character(),
+                    \ 'typed': l:typed,
+                    \ 'handle': v:true,
                     \ })
+
+        if mode() ==# 'i' && get(g:, 'LanguageClient_autoComplete', 0)
+            call s:ScheduleAutoComplete(l:typed)
+        endif
+    catch
+        call s:Debug('LanguageClient caught exception: ' . string(v:exception))
+    endtry
+endfunction
+
+function! LanguageClient#handleBufWritePre() abort
+    if &buftype !=# '' || &filetype ==# '' || expand('%') ==# ''
+        return
+    endif
+
+    try
+        if !empty(get(get(g:, 'LanguageClient_codeActionsOnSave', {}), &filetype, []))
+            call LanguageClient_runSync('LanguageClient#codeActionsOnSave', {})
+        endif
+        if index(get(g:, 'LanguageClient_formatOnSave', []), &filetype) >= 0
+            call LanguageClient_runSync('LanguageClient#formatOnSave', {})
+        endif
     catch
         call s:Debug('LanguageClient caught exception: ' . string(v:exception))
     endtry
@@ -838,8 +1342,38 @@ function! LanguageClient#handleBufDelete() abort
     endtry
 endfunction
 
+function! LanguageClient#handleInsertModeChanged() abort
+    " Ignore running a single command with i_CTRL-O, e.g., to cycle signatures.
+    if !get(g:, 'LanguageClient_autoSignatureHelp', 0)
+                \ || &buftype !=# '' || &filetype ==# '' || mode(1) ==# 'niI'
+        return
+    endif
+
+    try
+        call LanguageClient#Notify('languageClient/handleInsertModeChanged', {
+                    \ 'filename': LSP#filename(),
+                    \ })
+    catch
+        call s:Debug('LanguageClient caught exception: ' . string(v:exception))
+    endtry
+endfunction
+
+function! LanguageClient#handleBufWinEnter() abort
+    let l:list = get(b:, 'LanguageClient_diagnosticsList', {})
+    if empty(l:list)
+        return
+    endif
+
+    call s:SetDiagnosticsList(win_getid(), l:list.items, l:list.title)
+endfunction
+
 let s:last_cursor_line = -1
 function! LanguageClient#handleCursorMoved() abort
+    if get(g:, 'LanguageClient_autoDocumentHighlight', 0) && &buftype ==# ''
+                \ && has_key(get(g:, 'LanguageClient_serverCommands', {}), &filetype)
+        call s:ScheduleDocumentHighlight()
+    endif
+
     let l:cursor_line = getcurpos()[1] - 1
     if l:cursor_line == s:last_cursor_line
         return
@@ -859,7 +1393,54 @@ function! LanguageClient#handleCursorMoved() abort
     endtry
 endfunction
 
+let s:documentHighlightTimer = -1
+let s:documentHighlightSymbol = []
+
+" Keyword under cursor as [bufnr, line, start byte, end byte], or empty if there is none. Bytes
+" are 0-based, end exclusive.
+function! s:SymbolUnderCursor() abort
+    let l:line = getline('.')
+    if matchstr(l:line, '\%' . col('.') . 'c.') !~# '\k'
+        return []
+    endif
+    return [bufnr('%'), line('.'), match(l:line, '\k*\%' . col('.') . 'c'),
+                \ matchend(l:line, '\%' . col('.') . 'c\k*')]
+endfunction
+
+" Clear document highlight as soon as cursor leaves the symbol, and highlight the new symbol
+" once cursor rests on it.
+function! s:ScheduleDocumentHighlight() abort
+    let l:symbol = s:SymbolUnderCursor()
+    if l:symbol ==# s:documentHighlightSymbol
+        return
+    endif
+
+    let s:documentHighlightSymbol = l:symbol
+    call timer_stop(s:documentHighlightTimer)
+    call LanguageClient#clearDocumentHighlight()
+    if !empty(l:symbol)
+        let l:delay = get(g:, 'LanguageClient_autoDocumentHighlightDelay', 0.3)
+        let s:documentHighlightTimer = timer_start(float2nr(l:delay * 1000), function('s:DocumentHighlight'))
+    endif
+endfunction
+
+function! s:DocumentHighlight(timer) abort
+    if s:SymbolUnderCursor() !=# s:documentHighlightSymbol
+        return
+    endif
+
+    call LanguageClient#textDocument_documentHighlight({
+                \ 'symbol': s:documentHighlightSymbol,
+                \ 'handle': v:true,
+                \ }, 's:HandleOutputNothing')
+endfunction
+
 function! LanguageClient#handleCompleteDone() abort
+    if has('nvim')
+        call s:CloseCompletionItemDocumentation()
+        let s:completionMenu = {}
+    endif
+
     let user_data = get(v:completed_item, 'user_data', '')
     if user_data ==# ''
         return
@@ -877,6 +1458,27 @@ function! LanguageClient#handleCompleteDone() abort
     endtry
 endfunction
 
+function! LanguageClient#handleCompleteChanged() abort
+    if has('nvim')
+        call s:CloseCompletionItemDocumentation()
+        let s:completionMenu = copy(v:event)
+    endif
+
+    let user_data = get(v:event.completed_item, 'user_data', '')
+    if user_data ==# ''
+        return
+    endif
+
+    try
+        call LanguageClient#Notify('languageClient/handleCompleteChanged', {
+                    \ 'completed_item': v:event.completed_item,
+                    \ 'selected': complete_info(['selected']).selected,
+                    \ })
+    catch
+        call s:Debug('LanguageClient caught exception: ' . string(v:exception))
+    endtry
+endfunction
+
 function! LanguageClient#handleVimLeavePre() abort
     try
         if get(g:, 'LanguageClient_autoStop', 1)
@@ -923,6 +1525,52 @@ function! LanguageClient#explainErrorAtPoint(...) abort
     return LanguageClient#Call('languageClient/explainErrorAtPoint', l:params, l:Callback)
 endfunction
 
+function! LanguageClient#diagnosticRelatedLocations(...) abort
+    let l:Callback = get(a:000, 1, v:null)
+    let l:params = {
+                \ 'filename': LSP#filename(),
+                \ 'line': LSP#line(),
+                \ 'character': LSP#character(),
+                \ 'handle': s:IsFalse(l:Callback),
+                \ }
+    call extend(l:params, get(a:000, 0, {}))
+    return LanguageClient#Call('languageClient/diagnosticRelatedLocations', l:params, l:Callback)
+endfunction
+
+function! LanguageClient#diagnosticsSummary(...) abort
+    let l:Callback = get(a:000, 1, v:null)
+    let l:params = {
+                \ 'filename': LSP#filename(),
+                \ 'handle': s:IsFalse(l:Callback),
+                \ }
+    call extend(l:params, get(a:000, 0, {}))
+    return LanguageClient#Call('languageClient/diagnosticsSummary', l:params, l:Callback)
+endfunction
+
+function! LanguageClient#diagnosticNext(...) abort
+    let l:params = {
+                \ 'filename': LSP#filename(),
+                \ 'line': LSP#line(),
+                \ 'character': LSP#character(),
+                \ 'severity': v:null,
+                \ 'handle': v:true,
+                \ }
+    call extend(l:params, get(a:000, 0, {}))
+    return LanguageClient#Call('languageClient/diagnosticNext', l:params, v:null)
+endfunction
+
+function! LanguageClient#diagnosticPrev(...) abort
+    let l:params = {
+                \ 'filename': LSP#filename(),
+                \ 'line': LSP#line(),
+                \ 'character': LSP#character(),
+                \ 'severity': v:null,
+                \ 'handle': v:true,
+                \ }
+    call extend(l:params, get(a:000, 0, {}))
+    return LanguageClient#Call('languageClient/diagnosticPrev', l:params, v:null)
+endfunction
+
 let g:LanguageClient_omniCompleteResults = []
 function! LanguageClient#omniComplete(...) abort
     try
@@ -948,10 +1596,6 @@ function! LanguageClient#get_complete_start(input) abort
     return match(a:input, '\k*$')
 endfunction
 
-function! LanguageClient_filterCompletionItems(item, base) abort
-    return a:item.word =~# '^' . a:base
-endfunction
-
 let g:LanguageClient_completeResults = []
 function! LanguageClient#complete(findstart, base) abort
     if a:findstart
@@ -967,16 +1611,113 @@ function! LanguageClient#complete(findstart, base) abort
                     \ 'LanguageClient#omniComplete', {
                     \ 'character': LSP#character() + len(a:base),
                     \ 'complete_position': LSP#character(),
+                    \ 'base': a:base,
                     \ 'text': s:completeText,
                     \ })
-        let l:result = l:result is v:null ? [] : l:result
-        let l:filtered_items = []
-        for l:item in l:result
-            if LanguageClient_filterCompletionItems(l:item, a:base)
-                call add(l:filtered_items, l:item)
-            endif
-        endfor
-        return filtered_items
+        " Items are already filtered and ranked against base.
+        return s:FilterCompletionItems(l:result is v:null ? [] : l:result, a:base)
+    endif
+endfunction
+
+" Filter ranked items further with user defined LanguageClient_filterCompletionItems(), which is
+" deprecated.
+function! s:FilterCompletionItems(items, base) abort
+    if !exists('*LanguageClient_filterCompletionItems')
+        return a:items
+    endif
+    return filter(a:items, 'LanguageClient_filterCompletionItems(v:val, a:base)')
+endfunction
+
+" Jump to next tabstop of expanded snippet. Returns empty string, to be used in mappings with
+" <C-r>=.
+function! LanguageClient#snippetNext() abort
+    return s:JumpSnippet(1)
+endfunction
+
+function! LanguageClient#snippetPrev() abort
+    return s:JumpSnippet(-1)
+endfunction
+
+let s:autoCompleteTimer = -1
+
+" Request completion after a pause when enough of a word is typed, or immediately after a
+" possible trigger character.
+function! s:ScheduleAutoComplete(typed) abort
+    call timer_stop(s:autoCompleteTimer)
+    if a:typed ==# ''
+        return
+    endif
+
+    let l:delay = 0
+    if a:typed =~# '\k'
+        let l:word = matchstr(getline('.')[: col('.') - 2], '\k*$')
+        if strchars(l:word) < get(g:, 'LanguageClient_autoCompleteMinChars', 3)
+            return
+        endif
+        let l:delay = get(g:, 'LanguageClient_autoCompleteDelay', 0.1)
+    endif
+    let s:autoCompleteTimer = timer_start(float2nr(l:delay * 1000), function('s:AutoComplete'))
+endfunction
+
+function! s:AutoComplete(timer) abort
+    if mode() !=# 'i' || col('.') == 1
+        return
+    endif
+
+    let l:input = getline('.')[: col('.') - 2]
+    let l:start = LanguageClient#get_complete_start(l:input)
+    let l:Callback = function('s:HandleAutoComplete', [bufnr('%'), b:changedtick, l:start])
+    call LanguageClient#Call('languageClient/autoComplete', {
+                \ 'filename': LSP#filename(),
+                \ 'line': LSP#line(),
+                \ 'character': LSP#character(),
+                \ 'complete_position': l:start,
+                \ 'base': l:input[l:start :],
+                \ 'typed': matchstr(l:input, '.$'),
+                \ 'handle': v:false,
+                \ }, l:Callback)
+endfunction
+
+function! s:HandleAutoComplete(bufnr, changedtick, start, output) abort
+    " Discard late response, if anything changed since request.
+    if bufnr('%') != a:bufnr || b:changedtick != a:changedtick || mode() !=# 'i'
+        return
+    endif
+
+    let l:result = get(a:output, 'result', v:null)
+    if type(l:result) != s:TYPE.list
+        return
+    endif
+    let l:result = s:FilterCompletionItems(l:result, getline('.')[a:start : col('.') - 2])
+    if !empty(l:result)
+        call s:SetAutoCompleteopt()
+        call complete(a:start + 1, l:result)
+    endif
+endfunction
+
+let s:savedCompleteopt = v:null
+
+" Make sure no match is inserted while typing, until completion is done.
+function! s:SetAutoCompleteopt() abort
+    if s:savedCompleteopt is v:null
+        let s:savedCompleteopt = &completeopt
+    endif
+    set completeopt-=longest completeopt+=menuone,noinsert
+
+    augroup LanguageClient_autoCompleteopt
+        autocmd!
+        autocmd CompleteDone,InsertLeave * call s:RestoreCompleteopt()
+    augroup END
+endfunction
+
+function! s:RestoreCompleteopt() abort
+    augroup LanguageClient_autoCompleteopt
+        autocmd!
+    augroup END
+
+    if s:savedCompleteopt isnot v:null
+        let &completeopt = s:savedCompleteopt
+        let s:savedCompleteopt = v:null
     endif
 endfunction
 
@@ -992,6 +1733,16 @@ function! LanguageClient#textDocument_signatureHelp(...) abort
     return LanguageClient#Call('textDocument/signatureHelp', l:params, l:Callback)
 endfunction
 
+" Show next (delta 1) or previous (delta -1) overload of signature help being shown.
+function! LanguageClient#cycleSignature(delta, ...) abort
+    let l:params = {
+                \ 'delta': a:delta,
+                \ }
+    call extend(l:params, a:0 >= 1 ? a:1 : {})
+    let l:Callback = a:0 >= 2 ? a:2 : v:null
+    return LanguageClient#Call('languageClient/cycleSignature', l:params, l:Callback)
+endfunction
+
 function! LanguageClient#workspace_applyEdit(...) abort
     let l:params = {
                 \ 'edit': {},
@@ -1019,6 +1770,7 @@ endfunction
 " Set to 1 when the language server is busy (e.g. building the code).
 let g:LanguageClient_serverStatus = 0
 let g:LanguageClient_serverStatusMessage = ''
+let g:LanguageClient_diagnosticsSummary = {'error': 0, 'warning': 0, 'information': 0, 'hint': 0}
 
 function! LanguageClient#serverStatus() abort
     return g:LanguageClient_serverStatus
@@ -34,6 +34,10 @@ function! LanguageClient_textDocument_codeAction(...)
     return call('LanguageClient#textDocument_codeAction', a:000)
 endfunction
 
+function! LanguageClient_textDocument_visualCodeAction(...)
+    return call('LanguageClient#textDocument_visualCodeAction', a:000)
+endfunction
+
 function! LanguageClient_textDocument_completion(...)
     return call('LanguageClient#textDocument_completion', a:000)
 endfunction
@@ -46,6 +50,10 @@ function! LanguageClient_textDocument_rangeFormatting(...)
     return call('LanguageClient#textDocument_rangeFormatting', a:000)
 endfunction
 
+function! LanguageClient_textDocument_onTypeFormatting(...)
+    return call('LanguageClient#textDocument_onTypeFormatting', a:000)
+endfunction
+
 function! LanguageClient_textDocument_documentHighlight(...)
     return call('LanguageClient#textDocument_documentHighlight', a:000)
 endfunction
@@ -113,17 +121,24 @@ augroup languageClient
     autocmd!
     autocmd FileType * call LanguageClient#handleFileType()
     autocmd BufNewFile * call LanguageClient#handleBufNewFile()
+    autocmd BufWritePre * call LanguageClient#handleBufWritePre()
     autocmd BufWritePost * call LanguageClient#handleBufWritePost()
     autocmd BufDelete * call LanguageClient#handleBufDelete()
+    autocmd BufWinEnter * call LanguageClient#handleBufWinEnter()
+    autocmd InsertCharPre * call LanguageClient#handleInsertCharPre()
     autocmd TextChanged * call LanguageClient#handleTextChanged()
     autocmd TextChangedI * call LanguageClient#handleTextChanged()
     if exists('##TextChangedP')
         autocmd TextChangedP * call LanguageClient#handleTextChanged()
     endif
     autocmd CursorMoved * call LanguageClient#handleCursorMoved()
+    autocmd InsertEnter,InsertLeave * call LanguageClient#handleInsertModeChanged()
     autocmd VimLeavePre * call LanguageClient#handleVimLeavePre()
 
     autocmd CompleteDone * call LanguageClient#handleCompleteDone()
+    if exists('##CompleteChanged')
+        autocmd CompleteChanged * call LanguageClient#handleCompleteChanged()
+    endif
 
     if get(g:, 'LanguageClient_signatureHelpOnCompleteDone', 0)
         autocmd CompleteDone *
@@ -37,6 +37,7 @@ class Source(Base):
             self.vim.funcs.LanguageClient_omniComplete({
                 "character": character,
                 "complete_position": context["complete_position"],
+                "base": context["complete_str"],
             })
         return []
 
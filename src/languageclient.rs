@@ -50,6 +50,68 @@ impl State {
         Ok(serde_json::from_value(Value::Array(result))?)
     }
 
+    /// Position encoding negotiated with the language server.
+    fn get_position_encoding(&self, languageId: &str) -> PositionEncoding {
+        self.position_encodings
+            .get(languageId)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// How completion items are presented for a filetype, falling back to the global settings.
+    fn get_completion_format(&self, languageId: &str) -> CompletionFormat {
+        self.completionFormatByFiletype
+            .get(languageId)
+            .unwrap_or(&self.completionFormat)
+            .clone()
+    }
+
+    /// Text of a line in a text document, and the encoding used by its language server.
+    fn get_text_document_line(
+        &self,
+        filename: &str,
+        line: u64,
+    ) -> Option<(&str, PositionEncoding)> {
+        let document = self.text_documents.get(filename)?;
+        let text = document.text.lines().nth(line as usize)?;
+        Some((text, self.get_position_encoding(&document.language_id)))
+    }
+
+    /// Convert vim position (0-based line, byte column) to LSP position.
+    pub fn get_lsp_position(&self, filename: &str, line: u64, character: u64) -> Position {
+        let character = match self.get_text_document_line(filename, line) {
+            Some((text, encoding)) => byte_to_character(text, character as usize, encoding),
+            None => character,
+        };
+        Position { line, character }
+    }
+
+    /// Convert character of LSP position to vim byte column (0-based).
+    pub fn get_vim_character(&self, filename: &str, position: &Position) -> u64 {
+        match self.get_text_document_line(filename, position.line) {
+            Some((text, encoding)) => character_to_byte(text, position.character, encoding) as u64,
+            None => position.character,
+        }
+    }
+
+    /// Move cursor to LSP position in current buffer.
+    fn cursor_position(&mut self, languageId: &str, position: &Position) -> Fallible<()> {
+        let encoding = self.get_position_encoding(languageId);
+        let character = if encoding == PositionEncoding::UTF8 {
+            position.character
+        } else {
+            let text: String = self.call(None, "getline", json!([position.line + 1]))?;
+            character_to_byte(&text, position.character, encoding) as u64
+        };
+        self.cursor(position.line + 1, character + 1)
+    }
+
+    fn symbol_to_quickfix_entry(&self, sym: &SymbolInformation) -> Fallible<QuickfixEntry> {
+        let mut entry = QuickfixEntry::from_lsp(sym)?;
+        entry.col = Some(self.get_vim_character(&entry.filename, &sym.location.range.start) + 1);
+        Ok(entry)
+    }
+
     fn sync_settings(&mut self) -> Fallible<()> {
         let (loggingFile, loggingLevel, serverStderr): (
             Option<String>,
@@ -123,10 +185,73 @@ impl State {
                 .as_ref(),
         )?;
 
-        let (diagnosticsSignsMax, documentHighlightDisplay): (Option<u64>, Value) = self.eval(
+        let (
+            diagnosticsSignsMax,
+            documentHighlightDisplay,
+            useVirtualText,
+            virtualTextPrefix,
+            has_virtual_text,
+        ): (Option<u64>, Value, u64, String, u64) = self.eval(
             [
                 "get(g:, 'LanguageClient_diagnosticsSignsMax', v:null)",
                 "get(g:, 'LanguageClient_documentHighlightDisplay', {})",
+                "get(g:, 'LanguageClient_useVirtualText', get(g:, 'LanguageClient_showVirtualText', 0))",
+                "get(g:, 'LanguageClient_virtualTextPrefix', '> ')",
+                "has('nvim-0.3.2')",
+            ]
+                .as_ref(),
+        )?;
+
+        let (
+            diagnosticsMinSeverity,
+            diagnosticsIgnoreSources,
+            diagnosticsIgnoreCodes,
+            diagnosticsIgnorePaths,
+        ): (Option<String>, Vec<String>, Vec<Value>, Vec<String>) = self.eval(
+            [
+                "get(g:, 'LanguageClient_diagnosticsMinSeverity', v:null)",
+                "get(g:, 'LanguageClient_diagnosticsIgnoreSources', [])",
+                "get(g:, 'LanguageClient_diagnosticsIgnoreCodes', [])",
+                "get(g:, 'LanguageClient_diagnosticsIgnorePaths', [])",
+            ]
+                .as_ref(),
+        )?;
+
+        let (
+            completionDropDeprecated,
+            completionItemKinds,
+            completionDetailWidth,
+            completionFiletypeOverrides,
+        ): (u8, HashMap<String, String>, usize, HashMap<String, Value>) = self.eval(
+            [
+                "get(g:, 'LanguageClient_completionDropDeprecated', 0)",
+                "get(g:, 'LanguageClient_completionItemKinds', {})",
+                "get(g:, 'LanguageClient_completionDetailWidth', 0)",
+                "get(g:, 'LanguageClient_completionFiletypeOverrides', {})",
+            ]
+                .as_ref(),
+        )?;
+
+        let (useFloatingHover, has_floating_window, autoSignatureHelp): (u8, u8, u8) = self.eval(
+            [
+                "get(g:, 'LanguageClient_useFloatingHover', 0)",
+                "exists('*nvim_open_win') || exists('*popup_atcursor')",
+                "get(g:, 'LanguageClient_autoSignatureHelp', 0)",
+            ]
+                .as_ref(),
+        )?;
+
+        let (codeActionsOnSave, codeActionsOnSaveTimeout, formatOnSave, formatOnSaveTimeout): (
+            HashMap<String, Vec<String>>,
+            f64,
+            Vec<String>,
+            f64,
+        ) = self.eval(
+            [
+                "get(g:, 'LanguageClient_codeActionsOnSave', {})",
+                "get(g:, 'LanguageClient_codeActionsOnSaveTimeout', 1.0)",
+                "get(g:, 'LanguageClient_formatOnSave', [])",
+                "get(g:, 'LanguageClient_formatOnSaveTimeout', 1.0)",
             ]
                 .as_ref(),
         )?;
@@ -166,6 +291,32 @@ impl State {
             DiagnosticsList::Disabled
         };
 
+        let diagnosticsMinSeverity = if let Some(s) = diagnosticsMinSeverity {
+            Some(parse_diagnostic_severity(&s).ok_or_else(|| {
+                format_err!("Invalid option for LanguageClient_diagnosticsMinSeverity: {}", s)
+            })?)
+        } else {
+            None
+        };
+
+        // Codes could be either numbers or strings.
+        let diagnosticsIgnoreCodes: Vec<String> = diagnosticsIgnoreCodes
+            .into_iter()
+            .map(|code| match code {
+                Value::String(s) => s,
+                code => code.to_string(),
+            }).collect();
+
+        for p in &diagnosticsIgnorePaths {
+            if let Err(err) = glob::Pattern::new(p) {
+                bail!(
+                    "Invalid pattern in LanguageClient_diagnosticsIgnorePaths: {}. {}",
+                    p,
+                    err
+                );
+            }
+        }
+
         let windowLogMessageLevel = match windowLogMessageLevel.to_ascii_uppercase().as_str() {
             "ERROR" => MessageType::Error,
             "WARNING" => MessageType::Warning,
@@ -184,9 +335,36 @@ impl State {
         };
 
         let completionPreferTextEdit = completionPreferTextEdit == 1;
+        let completionDropDeprecated = completionDropDeprecated == 1;
+
+        let completionFormat = CompletionFormat {
+            kinds: completionItemKinds,
+            detailWidth: completionDetailWidth,
+        };
+        let mut completionFormatByFiletype = HashMap::new();
+        for (filetype, overrides) in completionFiletypeOverrides {
+            let mut format = completionFormat.clone();
+            if !overrides["completionItemKinds"].is_null() {
+                let kinds: HashMap<String, String> =
+                    serde_json::from_value(overrides["completionItemKinds"].clone())?;
+                format.kinds.extend(kinds);
+            }
+            if !overrides["completionDetailWidth"].is_null() {
+                format.detailWidth =
+                    serde_json::from_value(overrides["completionDetailWidth"].clone())?;
+            }
+            completionFormatByFiletype.insert(filetype, format);
+        }
+
+        let codeActionsOnSaveTimeout =
+            Duration::from_millis((codeActionsOnSaveTimeout * 1000.0) as u64);
+        let formatOnSaveTimeout = Duration::from_millis((formatOnSaveTimeout * 1000.0) as u64);
 
         let is_nvim = is_nvim == 1;
 
+        let useVirtualText = useVirtualText == 1 && has_virtual_text == 1;
+        let useFloatingHover = useFloatingHover == 1 && has_floating_window == 1;
+
         self.update(|state| {
             state.autoStart = autoStart;
             state.serverCommands.extend(serverCommands);
@@ -198,6 +376,14 @@ impl State {
                 serde_json::to_value(&state.diagnosticsDisplay)?.combine(&diagnosticsDisplay),
             )?;
             state.diagnosticsSignsMax = diagnosticsSignsMax;
+            state.diagnosticsFilter.minSeverity = diagnosticsMinSeverity;
+            state.diagnosticsFilter.ignoreSources = diagnosticsIgnoreSources;
+            state.diagnosticsFilter.ignoreCodes = diagnosticsIgnoreCodes;
+            state.diagnosticsFilter.ignorePaths = diagnosticsIgnorePaths;
+            state.useVirtualText = useVirtualText;
+            state.useFloatingHover = useFloatingHover;
+            state.autoSignatureHelp = autoSignatureHelp == 1;
+            state.virtualTextPrefix = virtualTextPrefix;
             state.documentHighlightDisplay = serde_json::from_value(
                 serde_json::to_value(&state.documentHighlightDisplay)?
                     .combine(&documentHighlightDisplay),
@@ -209,7 +395,14 @@ impl State {
             state.change_throttle = change_throttle;
             state.wait_output_timeout = wait_output_timeout;
             state.hoverPreview = hoverPreview;
+            state.codeActionsOnSave = codeActionsOnSave;
+            state.codeActionsOnSaveTimeout = codeActionsOnSaveTimeout;
+            state.formatOnSave = formatOnSave;
+            state.formatOnSaveTimeout = formatOnSaveTimeout;
             state.completionPreferTextEdit = completionPreferTextEdit;
+            state.completionDropDeprecated = completionDropDeprecated;
+            state.completionFormat = completionFormat;
+            state.completionFormatByFiletype = completionFormatByFiletype;
             state.loggingFile = loggingFile;
             state.loggingLevel = loggingLevel;
             state.serverStderr = serverStderr;
@@ -234,6 +427,20 @@ impl State {
         Ok(value)
     }
 
+    fn get_virtual_text_namespace(&mut self) -> Fallible<u64> {
+        if let Some(namespace) = self.virtual_text_namespace {
+            return Ok(namespace);
+        }
+
+        let namespace = self.call(
+            None,
+            "nvim_create_namespace",
+            json!(["LanguageClient_VirtualText"]),
+        )?;
+        self.virtual_text_namespace = Some(namespace);
+        Ok(namespace)
+    }
+
     fn define_signs(&mut self) -> Fallible<()> {
         info!("Defining signs");
 
@@ -312,7 +519,7 @@ impl State {
                 text_document: TextDocumentIdentifier {
                     uri: filename.to_url()?,
                 },
-                position: Position { line, character },
+                position: self.get_lsp_position(&filename, line, character),
             },
         )?;
 
@@ -320,6 +527,21 @@ impl State {
             return Ok(result);
         }
 
+        // Drop late response if cursor left the symbol it was requested for.
+        let (symbol,): (Option<(u64, u64, u64, u64)>,) =
+            self.gather_args(&[("symbol", "v:null")], params)?;
+        if let Some((bufnr, line, start, end)) = symbol {
+            let (current_bufnr, current_line, current_col): (u64, u64, u64) =
+                self.eval(["bufnr('%')", "line('.')", "col('.') - 1"].as_ref())?;
+            if (current_bufnr, current_line) != (bufnr, line)
+                || current_col < start
+                || current_col >= end
+            {
+                info!("Cursor left symbol, skip document highlight");
+                return Ok(result);
+            }
+        }
+
         let document_highlight: Option<Vec<DocumentHighlight>> =
             serde_json::from_value(result.clone())?;
         if let Some(document_highlight) = document_highlight {
@@ -328,8 +550,8 @@ impl State {
                 .map(|DocumentHighlight { range, kind }| {
                     Ok(Highlight {
                         line: range.start.line,
-                        character_start: range.start.character,
-                        character_end: range.end.character,
+                        character_start: self.get_vim_character(&filename, &range.start),
+                        character_end: self.get_vim_character(&filename, &range.end),
                         group: self
                             .documentHighlightDisplay
                             .get(
@@ -344,6 +566,12 @@ impl State {
                     })
                 }).collect::<Fallible<Vec<_>>>()?;
 
+            if !self.is_nvim {
+                self.set_document_highlight_matches(&highlights)?;
+                info!("End {}", lsp::request::DocumentHighlightRequest::METHOD);
+                return Ok(result);
+            }
+
             let buffer = self.call(None, "nvim_win_get_buf", json!([0]))?;
 
             let source = if let Some(hs) = self.document_highlight_source {
@@ -391,9 +619,48 @@ impl State {
         Ok(result)
     }
 
+    /// Replace document highlights with matches in current window, as vim has no buffer
+    /// highlights.
+    fn set_document_highlight_matches(&mut self, highlights: &[Highlight]) -> Fallible<()> {
+        self.clear_document_highlight_matches()?;
+
+        let mut match_groups: HashMap<&str, Vec<Vec<u64>>> = HashMap::new();
+        for hl in highlights {
+            let length = hl.character_end.saturating_sub(hl.character_start);
+            if length > 0 {
+                match_groups
+                    .entry(&hl.group)
+                    .or_default()
+                    .push(vec![hl.line + 1, hl.character_start + 1, length]);
+            }
+        }
+
+        let mut ids = vec![];
+        for (group, positions) in match_groups {
+            // Older vim accepts at most 8 positions per call.
+            for positions in positions.chunks(8) {
+                ids.push(self.call(None, "matchaddpos", json!([group, positions]))?);
+            }
+        }
+        self.document_highlight_match_ids = ids;
+        self.document_highlight_match_winid = self.call(None, "win_getid", json!([]))?;
+        Ok(())
+    }
+
+    fn clear_document_highlight_matches(&mut self) -> Fallible<()> {
+        let ids = std::mem::take(&mut self.document_highlight_match_ids);
+        if !ids.is_empty() {
+            let winid = self.document_highlight_match_winid;
+            self.notify(None, "s:MatchDeleteInWindow", json!([winid, ids]))?;
+        }
+        Ok(())
+    }
+
     pub fn languageClient_clearDocumentHighlight(&mut self, _: &Value) -> Fallible<()> {
         info!("Begin {}", NOTIFICATION__ClearDocumentHighlight);
 
+        self.clear_document_highlight_matches()?;
+
         if let Some(HighlightSource { buffer, source }) = self.document_highlight_source.take() {
             self.notify(
                 None,
@@ -425,59 +692,91 @@ impl State {
 
         self.edit(&None, &path)?;
 
-        let mut lines: Vec<String> = self.call(None, "getline", json!([1, '$']))?;
-        let lines_len_prev = lines.len();
+        let languageId: String = self.eval(VimVar::LanguageId)?;
+        let encoding = self.get_position_encoding(&languageId);
+        let lines_prev: Vec<String> = self.call(None, "getline", json!([1, '$']))?;
+        let mut lines = lines_prev.clone();
         let fixendofline = self.eval::<_, u8>("&fixendofline")? == 1;
         if lines.last().map(String::is_empty) == Some(false) && fixendofline {
             lines.push("".to_owned());
         }
 
-        let mut lines = apply_TextEdits(&lines, &edits)?;
+        let mut lines = apply_TextEdits(&lines, &edits, encoding)?;
 
         if lines.last().map(String::is_empty) == Some(true) && fixendofline {
             lines.pop();
         }
-        if lines.len() < lines_len_prev {
-            self.command(format!("{},{}d", lines.len() + 1, lines_len_prev))?;
-        }
-        if self.call::<_, i64>(None, "setline", json!([1, lines]))? != 0 {
-            bail!("Failed to set buffer content!");
-        }
+
+        // Only send the lines that actually changed, so that marks, folds and undo history of
+        // untouched lines are preserved.
+        let (start, end_prev, end) = get_changed_lines_range(&lines_prev, &lines);
+        self.set_lines(start, end_prev, &lines[start..end])?;
         debug!("End apply TextEdits");
         Ok(())
     }
 
-    fn update_quickfixlist(&mut self) -> Fallible<()> {
-        let qflist: Vec<_> = self
-            .diagnostics
-            .iter()
-            .flat_map(|(filename, diagnostics)| {
+    fn diagnostics_to_quickfix_entries(&self, filename: &str) -> Vec<QuickfixEntry> {
+        self.diagnostics
+            .get(filename)
+            .map(|diagnostics| {
                 diagnostics
                     .iter()
                     .map(|dn| QuickfixEntry {
                         filename: filename.to_owned(),
                         lnum: dn.range.start.line + 1,
-                        col: Some(dn.range.start.character + 1),
+                        col: Some(self.get_vim_character(filename, &dn.range.start) + 1),
                         nr: dn.code.clone().map(|ns| ns.to_string()),
                         text: Some(dn.message.to_owned()),
                         typ: dn.severity.map(|sev| sev.to_quickfix_entry_type()),
-                    }).collect::<Vec<_>>()
-            }).collect();
+                    }).collect()
+            }).unwrap_or_default()
+    }
 
-        let title = "[LC]: diagnostics";
+    /// Update diagnostics list after diagnostics of `filenames` changed.
+    ///
+    /// Lists not created by us are left untouched.
+    fn update_quickfixlist(&mut self, filenames: &[String]) -> Fallible<()> {
+        let title = DiagnosticsListTitle;
         match self.diagnosticsList {
-            DiagnosticsList::Quickfix => {
-                self.setqflist(&qflist, "r", title)?;
-            }
-            DiagnosticsList::Location => {
-                self.setloclist(&qflist, "r", title)?;
+            DiagnosticsList::Quickfix | DiagnosticsList::Location => {
+                let qflist: Vec<_> = self
+                    .diagnostics
+                    .keys()
+                    .flat_map(|filename| self.diagnostics_to_quickfix_entries(filename))
+                    .collect();
+                // -1 for quickfix list, 0 for location list of current window.
+                let winid = match self.diagnosticsList {
+                    DiagnosticsList::Quickfix => -1,
+                    _ => 0,
+                };
+                self.notify(None, "s:SetDiagnosticsList", json!([winid, qflist, title]))?;
             }
+            DiagnosticsList::Buffer => for filename in filenames {
+                let loclist = self.diagnostics_to_quickfix_entries(filename);
+                self.notify(
+                    None,
+                    "s:SetBufferDiagnosticsList",
+                    json!([filename, loclist, title]),
+                )?;
+            },
             DiagnosticsList::Disabled => {}
         }
 
         Ok(())
     }
 
+    /// Update diagnostics summary variables of all files and buffers of `filenames`.
+    fn update_diagnostics_summary(&mut self, filenames: &[String]) -> Fallible<()> {
+        let total = get_diagnostics_summary(self.diagnostics.values().flatten());
+        let buffers: HashMap<_, _> = filenames
+            .iter()
+            .map(|f| {
+                let summary = get_diagnostics_summary(self.diagnostics.get(f).unwrap_or(&vec![]));
+                (f.clone(), summary)
+            }).collect();
+        self.notify(None, "s:SetDiagnosticsSummary", json!([buffers, total]))
+    }
+
     fn process_diagnostics(&mut self, filename: &str, diagnostics: &[Diagnostic]) -> Fallible<()> {
         if !self.text_documents.contains_key(filename) {
             return Ok(());
@@ -493,18 +792,7 @@ impl State {
         let mut line_diagnostics = HashMap::new();
         for entry in diagnostics {
             let line = entry.range.start.line;
-            let mut msg = String::new();
-            if let Some(severity) = entry.severity {
-                msg += &format!("[{:?}]", severity);
-            }
-            if let Some(ref code) = entry.code {
-                let s = code.to_string();
-                if !s.is_empty() {
-                    msg += &format!("[{}]", s);
-                }
-            }
-            msg += &entry.message;
-            line_diagnostics.insert((filename.to_owned(), line), msg);
+            line_diagnostics.insert((filename.to_owned(), line), get_diagnostic_message(entry));
         }
         self.line_diagnostics.retain(|&(ref f, _), _| f != filename);
         self.line_diagnostics.extend(line_diagnostics);
@@ -536,9 +824,14 @@ impl State {
 
         let mut highlights = vec![];
         for dn in diagnostics {
-            let line = dn.range.start.line;
-            let character_start = dn.range.start.character;
-            let character_end = dn.range.end.character;
+            let start = (
+                dn.range.start.line,
+                self.get_vim_character(filename, &dn.range.start),
+            );
+            let end = (
+                dn.range.end.line,
+                self.get_vim_character(filename, &dn.range.end),
+            );
 
             let severity = dn.severity.unwrap_or(DiagnosticSeverity::Hint);
             let group = diagnosticsDisplay
@@ -546,25 +839,123 @@ impl State {
                 .ok_or_else(|| err_msg("Failed to get display"))?
                 .texthl
                 .clone();
-            // TODO: handle multi-line range.
-            let text = lines
-                .get(line as usize)
-                .and_then(|l| l.get((character_start as usize)..(character_end as usize)))
-                .map(ToOwned::to_owned)
-                .unwrap_or_default();
 
-            highlights.push(Highlight {
-                line,
-                character_start,
-                character_end,
-                group,
-                text,
-            });
+            highlights.extend(get_line_highlights(&lines, start, end, &group));
         }
         // dedup?
         self.highlights.insert(filename.to_owned(), highlights);
 
+        // Virtual texts. There might be multiple diagnostics for one line. Show only highest
+        // severity.
+        let mut virtual_texts: Vec<VirtualText> = vec![];
+        let mut diagnostics_by_severity: Vec<_> = diagnostics.iter().collect();
+        diagnostics_by_severity.sort_by_key(|dn| {
+            (
+                dn.range.start.line,
+                dn.severity.unwrap_or(DiagnosticSeverity::Hint).to_int().unwrap_or(4),
+            )
+        });
+        for dn in diagnostics_by_severity {
+            let line = dn.range.start.line;
+            if virtual_texts.last().map(|vt| vt.line) == Some(line) {
+                continue;
+            }
+
+            let severity = dn.severity.unwrap_or(DiagnosticSeverity::Hint);
+            let hl_group = diagnosticsDisplay
+                .get(&severity.to_int()?)
+                .ok_or_else(|| err_msg("Failed to get display"))?
+                .texthl
+                .clone();
+            virtual_texts.push(VirtualText {
+                line,
+                text: format!(
+                    "{}{}",
+                    self.virtualTextPrefix,
+                    dn.message.lines().collect::<Vec<_>>().join(" ")
+                ),
+                hl_group,
+            });
+        }
+        let changed = self.virtual_texts.get(filename) != Some(&virtual_texts);
+        self.virtual_texts.insert(filename.to_owned(), virtual_texts);
+        // Only visible lines are updated when cursor moves, so texts placed earlier outside of
+        // them would be left stale.
+        if changed {
+            if let Some(namespace) = self.virtual_text_namespace {
+                self.notify(None, "s:ClearVirtualTexts", json!([namespace, [filename]]))?;
+            }
+            self.virtual_texts_placed.remove(filename);
+        }
+
+        Ok(())
+    }
+
+    /// Render diagnostics of a buffer other than current one. All lines are rendered, as its
+    /// visible lines are unknown.
+    fn render_diagnostics_in_buffer(&mut self, filename: &str) -> Fallible<()> {
+        let bufnr: i64 = self.call(None, "bufnr", filename)?;
+        if bufnr == -1 {
+            return Ok(());
+        }
+
+        let signs = self.signs.get(filename).cloned().unwrap_or_default();
+        if Some(&signs) != self.signs_placed.get(filename) {
+            let empty = vec![];
+            let (signs, cmds) = get_command_update_signs(
+                self.signs_placed.get(filename).unwrap_or(&empty),
+                &signs,
+                filename,
+            );
+            self.signs_placed.insert(filename.to_owned(), signs);
+            self.command(&cmds)?;
+        }
+
+        if !self.is_nvim {
+            return Ok(());
+        }
+
+        if let Some(source) = self.highlight_source {
+            let highlights = self.highlights.get(filename).cloned().unwrap_or_default();
+            if Some(&highlights) != self.highlights_placed.get(filename) {
+                self.notify(
+                    None,
+                    "nvim_buf_clear_highlight",
+                    json!([bufnr, source, 0, -1]),
+                )?;
+                self.notify(None, "s:AddHighlights", json!([source, highlights, bufnr]))?;
+                self.highlights_placed
+                    .insert(filename.to_owned(), highlights);
+            }
+        }
+
+        if self.useVirtualText {
+            let virtual_texts = self.virtual_texts.get(filename).cloned().unwrap_or_default();
+            if Some(&virtual_texts) != self.virtual_texts_placed.get(filename) {
+                let namespace = self.get_virtual_text_namespace()?;
+                self.notify(
+                    None,
+                    "s:SetVirtualTexts",
+                    json!([namespace, 0, -1, virtual_texts, bufnr]),
+                )?;
+                self.virtual_texts_placed
+                    .insert(filename.to_owned(), virtual_texts);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Highlight diagnostics of current buffer with matches in current window, as vim has no
+    /// buffer highlights.
+    fn update_diagnostics_matches(
+        &mut self,
+        filename: &str,
+        diagnostics: &[Diagnostic],
+    ) -> Fallible<()> {
         if !self.is_nvim {
+            let diagnosticsDisplay = self.diagnosticsDisplay.clone();
+
             // Clear old highlights.
             let ids = self.highlight_match_ids.clone();
             self.notify(None, "s:MatchDelete", json!([ids]))?;
@@ -595,16 +986,14 @@ impl State {
                 let ranges: Vec<Vec<_>> = dns
                     .iter()
                     .flat_map(|dn| {
+                        let character_start = self.get_vim_character(filename, &dn.range.start);
+                        let character_end = self.get_vim_character(filename, &dn.range.end);
                         if dn.range.start.line == dn.range.end.line {
-                            let length = dn.range.end.character - dn.range.start.character;
+                            let length = character_end.saturating_sub(character_start);
                             // Vim line numbers are 1 off
                             // `matchaddpos` expects an array of [line, col, length]
                             // for each match.
-                            vec![vec![
-                                dn.range.start.line + 1,
-                                dn.range.start.character + 1,
-                                length,
-                            ]]
+                            vec![vec![dn.range.start.line + 1, character_start + 1, length]]
                         } else {
                             let mut middleLines: Vec<_> = (dn.range.start.line + 1
                                 ..dn.range.end.line)
@@ -612,11 +1001,10 @@ impl State {
                                 .collect();
                             let startLine = vec![
                                 dn.range.start.line + 1,
-                                dn.range.start.character + 1,
+                                character_start + 1,
                                 999_999, //Clear to the end of the line
                             ];
-                            let endLine =
-                                vec![dn.range.end.line + 1, 1, dn.range.end.character + 1];
+                            let endLine = vec![dn.range.end.line + 1, 1, character_end + 1];
                             middleLines.push(startLine);
                             middleLines.push(endLine);
                             middleLines
@@ -632,18 +1020,25 @@ impl State {
         Ok(())
     }
 
-    fn display_locations(&mut self, locations: &[Location], title: &str) -> Fallible<()> {
+    fn display_locations(
+        &mut self,
+        languageId: &str,
+        locations: &[Location],
+        title: &str,
+    ) -> Fallible<()> {
+        let encoding = self.get_position_encoding(languageId);
         let location_to_quickfix_entry =
             |state: &mut Self, loc: &Location| -> Fallible<QuickfixEntry> {
                 let filename = loc.uri.filepath()?.to_string_lossy().into_owned();
                 let start = loc.range.start;
                 let text = state.get_line(&filename, start.line).unwrap_or_default();
+                let character = character_to_byte(&text, start.character, encoding);
 
                 Ok(QuickfixEntry {
                     filename,
                     lnum: start.line + 1,
-                    col: Some(start.character + 1),
-                    text: Some(text),
+                    col: Some(character as u64 + 1),
+                    text: Some(text.trim().to_owned()),
                     nr: None,
                     typ: None,
                 })
@@ -658,13 +1053,14 @@ impl State {
                         let filename = loc.uri.filepath()?;
                         let start = loc.range.start;
                         let text = self.get_line(&filename, start.line).unwrap_or_default();
+                        let character = character_to_byte(&text, start.character, encoding);
                         let relpath = diff_paths(&filename, Path::new(&cwd)).unwrap_or(filename);
                         Ok(format!(
                             "{}:{}:{}:\t{}",
                             relpath.to_string_lossy(),
                             start.line + 1,
-                            start.character + 1,
-                            text
+                            character + 1,
+                            text.trim()
                         ))
                     }).collect();
                 let source = source?;
@@ -796,7 +1192,7 @@ impl State {
                 .ok_or_else(|| format_err!("Failed to get line! line: {}", line))??;
         }
 
-        Ok(text.trim().into())
+        Ok(text)
     }
 
     fn try_handle_command_by_client(&mut self, cmd: &Command) -> Fallible<bool> {
@@ -833,16 +1229,41 @@ impl State {
                 filenames.push(f.clone());
             }
         }
-        for f in filenames {
-            self.process_diagnostics(&f, &[])?;
+        let had_diagnostics = filenames
+            .iter()
+            .any(|f| self.diagnostics.get(f).is_some_and(|dns| !dns.is_empty()));
+        for f in &filenames {
+            self.process_diagnostics(f, &[])?;
+        }
+        if let Some(f) = filenames.first() {
+            self.update_diagnostics_matches(f, &[])?;
         }
         self.languageClient_handleCursorMoved(&Value::Null)?;
 
+        if let Some(namespace) = self.virtual_text_namespace {
+            let filenames: Vec<_> = self
+                .text_documents
+                .keys()
+                .filter(|f| f.starts_with(&root))
+                .cloned()
+                .collect();
+            self.notify(None, "s:ClearVirtualTexts", json!([namespace, filenames]))?;
+        }
+        self.virtual_texts.retain(|f, _| !f.starts_with(&root));
+        self.virtual_texts_placed.retain(|f, _| !f.starts_with(&root));
+
+        self.diagnostics_published
+            .retain(|f, _| !f.starts_with(&root));
         self.diagnostics.retain(|f, _| !f.starts_with(&root));
-        self.update_quickfixlist()?;
+        self.update_quickfixlist(&filenames)?;
+        self.update_diagnostics_summary(&filenames)?;
+        if had_diagnostics {
+            self.notify(None, "s:ExecuteAutocmd", "LanguageClientDiagnosticsSummaryChanged")?;
+        }
 
         self.writers.remove(languageId);
         self.child_ids.remove(languageId);
+        self.position_encodings.remove(languageId);
         self.last_cursor_line = 0;
         self.text_documents.retain(|f, _| !f.starts_with(&root));
         self.roots.remove(languageId);
@@ -862,6 +1283,21 @@ impl State {
         D: ToDisplay + ?Sized,
     {
         let bufname = "__LanguageClient__";
+        let (lines, regions) = to_display.to_display_with_regions();
+
+        if self.useFloatingHover {
+            // Floating window in neovim, popup in vim.
+            return self.notify(
+                None,
+                "s:OpenHoverPreview",
+                json!([
+                    bufname,
+                    lines,
+                    to_display.vim_filetype().unwrap_or_default(),
+                    regions,
+                ]),
+            );
+        }
 
         let cmd = "silent! pedit! +setlocal\\ buftype=nofile\\ nobuflisted\\ noswapfile\\ nonumber";
         let cmd = if let Some(ref ft) = to_display.vim_filetype() {
@@ -871,7 +1307,6 @@ impl State {
         };
         self.command(cmd)?;
 
-        let lines = to_display.to_display();
         if self.get(|state| Ok(state.is_nvim))? {
             let bufnr: u64 = serde_json::from_value(self.call(None, "bufnr", bufname)?)?;
             self.call::<_, Option<u8>>(
@@ -884,6 +1319,11 @@ impl State {
             // TODO: removing existing bottom lines.
         }
 
+        if !regions.is_empty() {
+            let winid: i64 = self.call(None, "bufwinid", bufname)?;
+            self.notify(None, "s:HighlightRegions", json!([winid, regions]))?;
+        }
+
         Ok(())
     }
 
@@ -914,43 +1354,103 @@ impl State {
 
         let trace = self.trace.clone();
 
-        let result: Value = self.call(
-            Some(&languageId),
-            lsp::request::Initialize::METHOD,
-            InitializeParams {
-                process_id: Some(u64::from(std::process::id())),
-                root_path: Some(root.clone()),
-                root_uri: Some(root.to_url()?),
-                initialization_options,
-                capabilities: ClientCapabilities {
-                    text_document: Some(TextDocumentClientCapabilities {
-                        completion: Some(CompletionCapability {
-                            completion_item: Some(CompletionItemCapability {
-                                snippet_support: Some(has_snippet_support),
-                                ..CompletionItemCapability::default()
-                            }),
-                            ..CompletionCapability::default()
+        let initialize_params = serde_json::to_value(InitializeParams {
+            process_id: Some(u64::from(std::process::id())),
+            root_path: Some(root.clone()),
+            root_uri: Some(root.to_url()?),
+            initialization_options,
+            capabilities: ClientCapabilities {
+                text_document: Some(TextDocumentClientCapabilities {
+                    completion: Some(CompletionCapability {
+                        completion_item: Some(CompletionItemCapability {
+                            snippet_support: Some(has_snippet_support),
+                            deprecated_support: Some(true),
+                            preselect_support: Some(true),
+                            ..CompletionItemCapability::default()
                         }),
-                        ..TextDocumentClientCapabilities::default()
+                        ..CompletionCapability::default()
+                    }),
+                    synchronization: Some(SynchronizationCapability {
+                        will_save_wait_until: Some(true),
+                        ..SynchronizationCapability::default()
                     }),
-                    workspace: Some(WorkspaceClientCapabilities {
-                        apply_edit: Some(true),
-                        did_change_watched_files: Some(GenericCapability {
-                            dynamic_registration: Some(true),
+                    code_action: Some(CodeActionCapability {
+                        code_action_literal_support: Some(CodeActionLiteralSupport {
+                            code_action_kind: CodeActionKindLiteralSupport {
+                                value_set: [
+                                    code_action_kind::QUICKFIX,
+                                    code_action_kind::REFACTOR,
+                                    code_action_kind::REFACTOR_EXTRACT,
+                                    code_action_kind::REFACTOR_INLINE,
+                                    code_action_kind::REFACTOR_REWRITE,
+                                    code_action_kind::SOURCE,
+                                    code_action_kind::SOURCE_ORGANIZE_IMPORTS,
+                                ].iter()
+                                    .map(|kind| kind.to_string())
+                                    .collect(),
+                            },
                         }),
-                        ..WorkspaceClientCapabilities::default()
+                        ..CodeActionCapability::default()
                     }),
-                    ..ClientCapabilities::default()
+                    ..TextDocumentClientCapabilities::default()
+                }),
+                workspace: Some(WorkspaceClientCapabilities {
+                    apply_edit: Some(true),
+                    did_change_watched_files: Some(GenericCapability {
+                        dynamic_registration: Some(true),
+                    }),
+                    ..WorkspaceClientCapabilities::default()
+                }),
+                ..ClientCapabilities::default()
+            },
+            trace,
+            workspace_folders: None,
+        })?;
+        // Vim columns are byte offsets, so prefer UTF-8 to avoid conversions. `offsetEncoding` is
+        // the clangd extension predating `positionEncodings`. `isPreferredSupport` and signature
+        // help label offsets are not yet in languageserver-types.
+        let initialize_params = initialize_params.combine(&json!({
+            "capabilities": {
+                "general": {
+                    "positionEncodings": ["utf-8", "utf-32", "utf-16"],
+                },
+                "offsetEncoding": ["utf-8", "utf-16"],
+                "textDocument": {
+                    "codeAction": {
+                        "isPreferredSupport": true,
+                    },
+                    "signatureHelp": {
+                        "signatureInformation": {
+                            "parameterInformation": {
+                                "labelOffsetSupport": true,
+                            },
+                            "activeParameterSupport": true,
+                        },
+                    },
                 },
-                trace,
-                workspace_folders: None,
             },
+        }));
+
+        let result: Value = self.call(
+            Some(&languageId),
+            lsp::request::Initialize::METHOD,
+            initialize_params,
         )?;
 
+        let position_encoding = result["capabilities"]["positionEncoding"]
+            .as_str()
+            .or_else(|| result["offsetEncoding"].as_str())
+            .map(PositionEncoding::from_str)
+            .unwrap_or_else(|| Ok(PositionEncoding::default()))?;
+        info!("Position encoding: {:?}", position_encoding);
+
         self.update(|state| {
             state
                 .capabilities
                 .insert(languageId.clone(), result.clone());
+            state
+                .position_encodings
+                .insert(languageId.clone(), position_encoding);
             Ok(())
         })?;
 
@@ -1009,7 +1509,7 @@ impl State {
                 text_document: TextDocumentIdentifier {
                     uri: filename.to_url()?,
                 },
-                position: Position { line, character },
+                position: self.get_lsp_position(&filename, line, character),
             },
         )?;
 
@@ -1065,7 +1565,7 @@ impl State {
             text_document: TextDocumentIdentifier {
                 uri: filename.to_url()?,
             },
-            position: Position { line, character },
+            position: self.get_lsp_position(&filename, line, character),
         })?.combine(params);
 
         let result = self.call(Some(&languageId), &method, &params)?;
@@ -1083,25 +1583,21 @@ impl State {
             }
             Some(GotoDefinitionResponse::Scalar(loc)) => {
                 self.edit(&goto_cmd, loc.uri.filepath()?)?;
-                self.cursor(loc.range.start.line + 1, loc.range.start.character + 1)?;
+                self.cursor_position(&languageId, &loc.range.start)?;
             }
             Some(GotoDefinitionResponse::Array(arr)) => match arr.len() {
                 0 => self.echowarn("Not found!")?,
                 1 => {
                     let loc = arr.get(0).ok_or_else(|| err_msg("Not found!"))?;
                     self.edit(&goto_cmd, loc.uri.filepath()?)?;
-                    self.cursor(loc.range.start.line + 1, loc.range.start.character + 1)?;
-                    let cur_file: String = self.eval("expand('%')")?;
-                    self.echomsg_ellipsis(format!(
-                        "[LC]: {} {}:{}",
-                        cur_file,
-                        loc.range.start.line + 1,
-                        loc.range.start.character + 1
-                    ))?;
+                    self.cursor_position(&languageId, &loc.range.start)?;
+                    let (cur_file, line, col): (String, u64, u64) =
+                        self.eval(["expand('%')", "line('.')", "col('.')"].as_ref())?;
+                    self.echomsg_ellipsis(format!("[LC]: {} {}:{}", cur_file, line, col))?;
                 }
                 _ => {
                     let title = format!("[LC]: search for {}", word);
-                    self.display_locations(&arr, &title)?
+                    self.display_locations(&languageId, &arr, &title)?
                 }
             },
         };
@@ -1150,7 +1646,7 @@ impl State {
                 text_document: TextDocumentIdentifier {
                     uri: filename.to_url()?,
                 },
-                position: Position { line, character },
+                position: self.get_lsp_position(&filename, line, character),
                 new_name,
             },
         )?;
@@ -1201,7 +1697,7 @@ impl State {
                         format!(
                             "{}:{}:\t{}\t\t{:?}",
                             start.line + 1,
-                            start.character + 1,
+                            self.get_vim_character(&filename, &start) + 1,
                             sym.name,
                             sym.kind
                         )
@@ -1214,13 +1710,19 @@ impl State {
                 )?;
             }
             SelectionUI::Quickfix => {
-                let list: Fallible<Vec<_>> = symbols.iter().map(QuickfixEntry::from_lsp).collect();
+                let list: Fallible<Vec<_>> = symbols
+                    .iter()
+                    .map(|sym| self.symbol_to_quickfix_entry(sym))
+                    .collect();
                 let list = list?;
                 self.setqflist(&list, " ", &title)?;
                 self.echo("Document symbols populated to quickfix list.")?;
             }
             SelectionUI::LocationList => {
-                let list: Fallible<Vec<_>> = symbols.iter().map(QuickfixEntry::from_lsp).collect();
+                let list: Fallible<Vec<_>> = symbols
+                    .iter()
+                    .map(|sym| self.symbol_to_quickfix_entry(sym))
+                    .collect();
                 let list = list?;
                 self.setloclist(&list, " ", &title)?;
                 self.echo("Document symbols populated to location list.")?;
@@ -1251,48 +1753,53 @@ impl State {
             params,
         )?;
 
+        // Range of visual selection, in lines.
+        let (range_start_line, range_end_line, only): (
+            Option<u64>,
+            Option<u64>,
+            Option<Vec<String>>,
+        ) = self.gather_args(
+            &[
+                ("LSP#range_start_line()", "v:null"),
+                ("LSP#range_end_line()", "v:null"),
+                ("only", "v:null"),
+            ],
+            params,
+        )?;
+
         // Unify filename.
         let filename = filename.canonicalize();
 
-        let diagnostics: Vec<_> = self
-            .diagnostics
-            .get(&filename)
-            .unwrap_or(&vec![])
-            .iter()
-            .filter(|dn| {
-                let start = dn.range.start;
-                let end = dn.range.end;
-                (line, character) >= (start.line, start.character)
-                    && (line, character) < (end.line, end.character)
-            }).cloned()
-            .collect();
-        let result: Value = self.call(
-            Some(&languageId),
-            lsp::request::CodeActionRequest::METHOD,
-            CodeActionParams {
-                text_document: TextDocumentIdentifier {
-                    uri: filename.to_url()?,
-                },
-                range: Range {
-                    start: Position { line, character },
-                    end: Position { line, character },
+        let range = match (range_start_line, range_end_line) {
+            (Some(start_line), Some(end_line)) => Range {
+                start: Position {
+                    line: start_line,
+                    character: 0,
                 },
-                context: CodeActionContext {
-                    diagnostics,
-                    only: None,
+                end: Position {
+                    line: end_line,
+                    character: 0,
                 },
             },
-        )?;
-
-        let commands: Vec<Command> = serde_json::from_value(result.clone())?;
+            _ => {
+                let position = self.get_lsp_position(&filename, line, character);
+                Range {
+                    start: position,
+                    end: position,
+                }
+            }
+        };
+        let diagnostics = self.get_diagnostics_in(&filename, &range);
+        let (result, actions) =
+            self.request_code_actions(&languageId, &filename, range, diagnostics, only, None)?;
 
-        let source: Vec<_> = commands
+        let source: Vec<_> = actions
             .iter()
-            .map(|cmd| format!("{}: {}", cmd.command, cmd.title))
+            .map(|action| format!("{}: {}", action.label(), action.title()))
             .collect();
 
         self.update(|state| {
-            state.stashed_codeAction_commands = commands;
+            state.stashed_codeActions = actions;
             Ok(())
         })?;
 
@@ -1306,35 +1813,244 @@ impl State {
         Ok(result)
     }
 
-    pub fn textDocument_completion(&mut self, params: &Value) -> Fallible<Value> {
-        self.textDocument_didChange(params)?;
-        info!("Begin {}", lsp::request::Completion::METHOD);
-
-        let (languageId, filename, line, character, handle): (
-            String,
-            String,
-            u64,
-            u64,
-            bool,
-        ) = self.gather_args(
-            &[
-                VimVar::LanguageId,
-                VimVar::Filename,
-                VimVar::Line,
-                VimVar::Character,
-                VimVar::Handle,
-            ],
-            params,
-        )?;
+    /// Diagnostics applying to range, or to position if range is empty.
+    fn get_diagnostics_in(&self, filename: &str, range: &Range) -> Vec<Diagnostic> {
+        self.diagnostics
+            .get(filename)
+            .unwrap_or(&vec![])
+            .iter()
+            .filter(|dn| is_diagnostic_in_range(&dn.range, range))
+            .cloned()
+            .collect()
+    }
 
-        let result = self.call(
-            Some(&languageId),
-            lsp::request::Completion::METHOD,
-            TextDocumentPositionParams {
+    fn request_code_actions(
+        &mut self,
+        languageId: &str,
+        filename: &str,
+        range: Range,
+        diagnostics: Vec<Diagnostic>,
+        only: Option<Vec<String>>,
+        deadline: Option<Instant>,
+    ) -> Fallible<(Value, Vec<CodeActionOrCommand>)> {
+        let result: Value = self.call_until(
+            Some(languageId),
+            lsp::request::CodeActionRequest::METHOD,
+            CodeActionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: filename.to_url()?,
+                },
+                range,
+                context: CodeActionContext {
+                    diagnostics,
+                    only: only.clone(),
+                },
+            },
+            deadline,
+        )?;
+
+        let actions: Option<Vec<CodeActionOrCommand>> = serde_json::from_value(result.clone())?;
+        let mut actions = actions.unwrap_or_default();
+        // Servers are free to ignore `only`.
+        if let Some(only) = only {
+            actions.retain(|action| match action {
+                CodeActionOrCommand::CodeAction(CodeActionLiteral {
+                    kind: Some(ref kind),
+                    ..
+                }) => only.iter().any(|base| is_code_action_kind(kind, base)),
+                _ => false,
+            });
+        }
+        Ok((result, actions))
+    }
+
+    /// Apply edit of a code action then execute its command.
+    fn execute_code_action(
+        &mut self,
+        action: &CodeActionOrCommand,
+        deadline: Option<Instant>,
+    ) -> Fallible<()> {
+        let (edit, command) = match action {
+            CodeActionOrCommand::Command(cmd) => (None, Some(cmd)),
+            CodeActionOrCommand::CodeAction(action) => {
+                (action.edit.as_ref(), action.command.as_ref())
+            }
+        };
+
+        if let Some(edit) = edit {
+            self.apply_WorkspaceEdit(edit, &Value::Null)?;
+        }
+
+        if let Some(cmd) = command {
+            if !self.try_handle_command_by_client(cmd)? {
+                let (languageId,): (String,) =
+                    self.gather_args(&[VimVar::LanguageId], &Value::Null)?;
+                self.execute_command(
+                    &languageId,
+                    cmd.command.clone(),
+                    cmd.arguments.clone().unwrap_or_default(),
+                    deadline,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn languageClient_quickFix(&mut self, params: &Value) -> Fallible<Value> {
+        self.textDocument_didChange(params)?;
+        info!("Begin {}", REQUEST__QuickFix);
+        let (languageId, filename, line, character): (String, String, u64, u64) = self
+            .gather_args(
+                &[
+                    VimVar::LanguageId,
+                    VimVar::Filename,
+                    VimVar::Line,
+                    VimVar::Character,
+                ],
+                params,
+            )?;
+        let filename = filename.canonicalize();
+
+        let position = self.get_lsp_position(&filename, line, character);
+        let diagnostics = self.get_diagnostics_in(
+            &filename,
+            &Range {
+                start: position,
+                end: position,
+            },
+        );
+        let range = match diagnostics.first() {
+            Some(dn) => dn.range,
+            None => {
+                self.echowarn("No diagnostics found")?;
+                return Ok(Value::Null);
+            }
+        };
+        let (_, actions) = self.request_code_actions(
+            &languageId,
+            &filename,
+            range,
+            diagnostics,
+            Some(vec![code_action_kind::QUICKFIX.to_owned()]),
+            None,
+        )?;
+
+        match get_preferred_quickfix(&actions).cloned() {
+            Some(action) => {
+                self.execute_code_action(&action, None)?;
+                self.echo(action.title())?;
+            }
+            None => self.echowarn("No preferred quick fix found")?,
+        }
+
+        info!("End {}", REQUEST__QuickFix);
+        Ok(Value::Null)
+    }
+
+    /// Run code actions of configured kinds on whole document, typically before write.
+    ///
+    /// For each kind, the first action returned is applied. Kinds not done before timeout are
+    /// skipped.
+    pub fn languageClient_codeActionsOnSave(&mut self, params: &Value) -> Fallible<Value> {
+        info!("Begin {}", REQUEST__CodeActionsOnSave);
+        let (languageId, filename): (String, String) =
+            self.gather_args(&[VimVar::LanguageId, VimVar::Filename], params)?;
+        let kinds = self
+            .codeActionsOnSave
+            .get(&languageId)
+            .cloned()
+            .unwrap_or_default();
+        if kinds.is_empty() || !self.writers.contains_key(&languageId) {
+            return Ok(Value::Null);
+        }
+
+        let filename = filename.canonicalize();
+        let deadline = Instant::now() + self.codeActionsOnSaveTimeout;
+        for kind in kinds {
+            // Document might have been changed by previous action.
+            self.textDocument_didChange(&Value::Null)?;
+            let (last_line, last_line_length): (u64, u64) =
+                self.eval(["line('$') - 1", "strlen(getline('$'))"].as_ref())?;
+            let range = Range {
+                start: Position {
+                    line: 0,
+                    character: 0,
+                },
+                end: self.get_lsp_position(&filename, last_line, last_line_length),
+            };
+            let diagnostics = self.diagnostics.get(&filename).cloned().unwrap_or_default();
+            let actions = match self.request_code_actions(
+                &languageId,
+                &filename,
+                range,
+                diagnostics,
+                Some(vec![kind.clone()]),
+                Some(deadline),
+            ) {
+                Ok((_, actions)) => actions,
+                Err(err) => {
+                    warn!("Failed to get code actions on save. kind: {}, {}", kind, err);
+                    self.echowarn(format!("Skipped code action on save: {}", kind))?;
+                    break;
+                }
+            };
+
+            if let Some(action) = actions.first() {
+                if let Err(err) = self.execute_code_action(action, Some(deadline)) {
+                    warn!("Failed to execute code action on save. kind: {}, {}", kind, err);
+                    self.echowarn(format!("Skipped code action on save: {}", kind))?;
+                    break;
+                }
+            }
+        }
+        self.textDocument_didChange(&Value::Null)?;
+
+        info!("End {}", REQUEST__CodeActionsOnSave);
+        Ok(Value::Null)
+    }
+
+    pub fn textDocument_completion(&mut self, params: &Value) -> Fallible<Value> {
+        self.textDocument_didChange(params)?;
+        info!("Begin {}", lsp::request::Completion::METHOD);
+
+        let (languageId, filename, line, character, handle): (
+            String,
+            String,
+            u64,
+            u64,
+            bool,
+        ) = self.gather_args(
+            &[
+                VimVar::LanguageId,
+                VimVar::Filename,
+                VimVar::Line,
+                VimVar::Character,
+                VimVar::Handle,
+            ],
+            params,
+        )?;
+
+        let (trigger_character,): (Option<String>,) =
+            self.gather_args(&[("triggerCharacter", "v:null")], params)?;
+        let trigger_kind = if trigger_character.is_some() {
+            CompletionTriggerKind::TriggerCharacter
+        } else {
+            CompletionTriggerKind::Invoked
+        };
+
+        let result = self.call(
+            Some(&languageId),
+            lsp::request::Completion::METHOD,
+            CompletionParams {
                 text_document: TextDocumentIdentifier {
                     uri: filename.to_url()?,
                 },
-                position: Position { line, character },
+                position: self.get_lsp_position(&filename, line, character),
+                context: Some(CompletionContext {
+                    trigger_kind,
+                    trigger_character,
+                }),
             },
         )?;
 
@@ -1373,7 +2089,7 @@ impl State {
                 text_document: TextDocumentIdentifier {
                     uri: filename.to_url()?,
                 },
-                position: Position { line, character },
+                position: self.get_lsp_position(&filename, line, character),
             },
         )?;
 
@@ -1381,44 +2097,128 @@ impl State {
             return Ok(result);
         }
 
-        let help: SignatureHelp = serde_json::from_value(result)?;
-        if help.signatures.is_empty() {
-            return Ok(Value::Null);
+        let help: Option<SignatureHelpResult> = serde_json::from_value(result)?;
+        let help = help.filter(|help| !help.signatures.is_empty());
+        let shown = self.update(|state| {
+            Ok(match help {
+                Some(ref help) => state
+                    .signature_help
+                    .insert(filename.clone(), help.clone())
+                    .is_some(),
+                None => state.signature_help.remove(&filename).is_some(),
+            })
+        })?;
+        match help {
+            Some(help) => self.show_signature_help(&languageId, &help)?,
+            // Cursor left the call, e.g., while typing arguments.
+            None if shown && self.useFloatingHover => {
+                self.notify(None, "LanguageClient#closeFloatingHover", json!([]))?
+            }
+            None if shown => self.echo("")?,
+            None => (),
         }
-        let active_signature = help
+
+        info!("End {}", lsp::request::SignatureHelpRequest::METHOD);
+        Ok(Value::Null)
+    }
+
+    fn get_signature_help_triggers(&self, languageId: &str) -> Vec<String> {
+        self.capabilities
+            .get(languageId)
+            .map(|c| c["capabilities"]["signatureHelpProvider"]["triggerCharacters"].clone())
+            .and_then(|triggers| serde_json::from_value(triggers).ok())
+            .unwrap_or_default()
+    }
+
+    /// Show active signature in floating window or echo area, with active parameter highlighted.
+    fn show_signature_help(
+        &mut self,
+        languageId: &str,
+        help: &SignatureHelpResult,
+    ) -> Fallible<()> {
+        let index = help.active_signature.unwrap_or(0).to_usize()?;
+        let signature = help
             .signatures
-            .get(help.active_signature.unwrap_or(0).to_usize()?)
+            .get(index)
             .ok_or_else(|| err_msg("Failed to get active signature"))?;
-        let active_parameter: Option<&ParameterInformation>;
-        if let Some(ref parameters) = active_signature.parameters {
-            active_parameter = parameters.get(help.active_parameter.unwrap_or(0).to_usize()?);
-        } else {
-            active_parameter = None;
+        let active_parameter = signature
+            .active_parameter
+            .or(help.active_parameter)
+            .unwrap_or(0)
+            .to_usize()?;
+        let parameter = signature
+            .parameters
+            .as_ref()
+            .and_then(|parameters| parameters.get(active_parameter));
+
+        let encoding = self.get_position_encoding(languageId);
+        let mut range = parameter
+            .and_then(|p| get_parameter_range(&signature.label, &p.label, encoding))
+            .filter(|&(_, end)| !signature.label[..end].contains('\n'));
+        let mut label = signature.label.clone();
+        if help.signatures.len() > 1 {
+            // Indicate overloads that can be cycled through.
+            let overload = format!("({}/{}) ", index + 1, help.signatures.len());
+            range = range.map(|(start, end)| (start + overload.len(), end + overload.len()));
+            label.insert_str(0, &overload);
         }
 
-        if let Some(active_parameter) = active_parameter {
-            let mut cmd = "echo".to_owned();
-            let chunks: Vec<&str> = active_signature
-                .label
-                .split(&active_parameter.label)
-                .collect();
-            if chunks.len() == 2 {
-                let begin = chunks.get(0).cloned().unwrap_or_default();
-                let end = chunks.get(1).cloned().unwrap_or_default();
-                cmd += &format!(
-                    " | echon '{}' | echohl WarningMsg | echon '{}' | echohl None | echon '{}'",
-                    begin, active_parameter.label, end
-                );
-            } else {
-                // Active parameter is not part of signature.
-                cmd += &format!(" | echo '{}'", active_signature.label);
+        if self.useFloatingHover {
+            let mut lines = label.to_display();
+            let documentation = parameter
+                .and_then(|p| p.documentation.as_ref())
+                .or(signature.documentation.as_ref());
+            if let Some(documentation) = documentation {
+                lines.push(String::new());
+                lines.extend(documentation.to_string().to_display());
+            }
+            self.preview(lines.join("\n").as_str())?;
+            if let Some((start, end)) = range {
+                self.notify(
+                    None,
+                    "s:HighlightFloatingHover",
+                    json!(["WarningMsg", 1, start + 1, end - start]),
+                )?;
             }
-            self.command(&cmd)?;
+        } else if let Some((start, end)) = range {
+            self.command(format!(
+                "echo '' | echon '{}' | echohl WarningMsg | echon '{}' | echohl None | echon '{}'",
+                escape_single_quote(&label[..start]),
+                escape_single_quote(&label[start..end]),
+                escape_single_quote(&label[end..]),
+            ))?;
         } else {
-            self.echo(&active_signature.label)?;
+            self.echo(&label)?;
         }
 
-        info!("End {}", lsp::request::SignatureHelpRequest::METHOD);
+        Ok(())
+    }
+
+    /// Show next or previous overload of signature help being shown.
+    pub fn languageClient_cycleSignature(&mut self, params: &Value) -> Fallible<Value> {
+        info!("Begin {}", REQUEST__CycleSignature);
+        let (languageId, filename, delta): (String, String, i64) = self.gather_args(
+            &[
+                VimVar::LanguageId.to_key().as_str(),
+                VimVar::Filename.to_key().as_str(),
+                "delta",
+            ],
+            params,
+        )?;
+
+        let help = self.update(|state| {
+            Ok(state.signature_help.get_mut(&filename).map(|help| {
+                let count = help.signatures.len() as i64;
+                let index = help.active_signature.unwrap_or(0) as i64 + delta;
+                help.active_signature = Some(((index % count + count) % count) as u64);
+                help.clone()
+            }))
+        })?;
+        if let Some(help) = help {
+            self.show_signature_help(&languageId, &help)?;
+        }
+
+        info!("End {}", REQUEST__CycleSignature);
         Ok(Value::Null)
     }
 
@@ -1441,6 +2241,110 @@ impl State {
         Ok(result)
     }
 
+    /// Formatting options of current buffer.
+    ///
+    /// Properties other than tab size and spaces are taken from buffer options and from
+    /// `LanguageClient_formattingOptions`, buffer-local value first.
+    fn get_formatting_options(&mut self) -> Fallible<FormattingOptions> {
+        let (tab_size, insert_spaces, fixendofline, user_properties): (
+            u64,
+            u64,
+            u64,
+            HashMap<String, FormattingProperty>,
+        ) = self.eval(
+            [
+                "shiftwidth()",
+                "&expandtab",
+                "&fixendofline",
+                "get(b:, 'LanguageClient_formattingOptions', \
+                 get(g:, 'LanguageClient_formattingOptions', {}))",
+            ].as_ref(),
+        )?;
+        let insert_spaces = insert_spaces == 1;
+        let mut properties = hashmap!{
+            "insertFinalNewline".to_owned() => FormattingProperty::Bool(fixendofline == 1),
+        };
+        properties.extend(user_properties);
+        Ok(FormattingOptions {
+            tab_size,
+            insert_spaces,
+            properties,
+        })
+    }
+
+    /// Format document before write, using `willSaveWaitUntil` if supported by server.
+    ///
+    /// Formatting is skipped if server does not respond before timeout.
+    pub fn languageClient_formatOnSave(&mut self, params: &Value) -> Fallible<Value> {
+        info!("Begin {}", REQUEST__FormatOnSave);
+        let (languageId, filename): (String, String) =
+            self.gather_args(&[VimVar::LanguageId, VimVar::Filename], params)?;
+        if !self.formatOnSave.contains(&languageId) || !self.writers.contains_key(&languageId) {
+            return Ok(Value::Null);
+        }
+
+        self.textDocument_didChange(&Value::Null)?;
+        let deadline = Instant::now() + self.formatOnSaveTimeout;
+        let capabilities = self
+            .capabilities
+            .get(&languageId)
+            .cloned()
+            .unwrap_or_default();
+        let text_document = TextDocumentIdentifier {
+            uri: filename.to_url()?,
+        };
+        let capabilities = &capabilities["capabilities"];
+        let result: Fallible<Option<Vec<TextEdit>>> =
+            if capabilities["textDocumentSync"]["willSaveWaitUntil"] == true {
+                // Note: this is a request, though languageserver-types has it as notification.
+                self.call_until(
+                    Some(&languageId),
+                    lsp::notification::WillSaveWaitUntil::METHOD,
+                    WillSaveTextDocumentParams {
+                        text_document,
+                        reason: TextDocumentSaveReason::Manual,
+                    },
+                    Some(deadline),
+                )
+            } else if capabilities["documentFormattingProvider"] == true
+                || capabilities["documentFormattingProvider"].is_object()
+            {
+                let options = self.get_formatting_options()?;
+                self.call_until(
+                    Some(&languageId),
+                    lsp::request::Formatting::METHOD,
+                    DocumentFormattingParams {
+                        text_document,
+                        options,
+                    },
+                    Some(deadline),
+                )
+            } else {
+                Ok(None)
+            };
+
+        let text_edits = match result {
+            Ok(text_edits) => text_edits.unwrap_or_default(),
+            Err(err) => {
+                warn!("Failed to format on save: {}", err);
+                self.echowarn(format!("Skipped formatting on save: {}", err))?;
+                return Ok(Value::Null);
+            }
+        };
+        if text_edits.is_empty() {
+            return Ok(Value::Null);
+        }
+        let edit = lsp::WorkspaceEdit {
+            changes: Some(hashmap!{filename.to_url()? => text_edits}),
+            document_changes: None,
+        };
+        self.apply_WorkspaceEdit(&edit, &Value::Null)?;
+        self.textDocument_didChange(&Value::Null)?;
+
+        info!("End {}", REQUEST__FormatOnSave);
+        Ok(Value::Null)
+    }
+
     pub fn textDocument_formatting(&mut self, params: &Value) -> Fallible<Value> {
         self.textDocument_didChange(params)?;
         info!("Begin {}", lsp::request::Formatting::METHOD);
@@ -1449,9 +2353,7 @@ impl State {
             params,
         )?;
 
-        let (tab_size, insert_spaces): (u64, u64) =
-            self.eval(["shiftwidth()", "&expandtab"].as_ref())?;
-        let insert_spaces = insert_spaces == 1;
+        let options = self.get_formatting_options()?;
         let result = self.call(
             Some(&languageId),
             lsp::request::Formatting::METHOD,
@@ -1459,11 +2361,7 @@ impl State {
                 text_document: TextDocumentIdentifier {
                     uri: filename.to_url()?,
                 },
-                options: FormattingOptions {
-                    tab_size,
-                    insert_spaces,
-                    properties: HashMap::new(),
-                },
+                options,
             },
         )?;
 
@@ -1485,26 +2383,25 @@ impl State {
     pub fn textDocument_rangeFormatting(&mut self, params: &Value) -> Fallible<Value> {
         self.textDocument_didChange(params)?;
         info!("Begin {}", lsp::request::RangeFormatting::METHOD);
-        let (languageId, filename, handle, start_line, end_line): (
+        // Range start and end, as vim lines and byte columns.
+        let (languageId, filename, handle, (start_line, start_col), (end_line, end_col)): (
             String,
             String,
             bool,
-            u64,
-            u64,
+            (u64, u64),
+            (u64, u64),
         ) = self.gather_args(
             &[
                 VimVar::LanguageId.to_key().as_str(),
                 VimVar::Filename.to_key().as_str(),
                 VimVar::Handle.to_key().as_str(),
-                "LSP#range_start_line()",
-                "LSP#range_end_line()",
+                "LSP#range_start()",
+                "LSP#range_end()",
             ],
             params,
         )?;
 
-        let (tab_size, insert_spaces): (u64, u64) =
-            self.eval(["shiftwidth()", "&expandtab"].as_ref())?;
-        let insert_spaces = insert_spaces == 1;
+        let options = self.get_formatting_options()?;
         let result = self.call(
             Some(&languageId),
             lsp::request::RangeFormatting::METHOD,
@@ -1512,20 +2409,10 @@ impl State {
                 text_document: TextDocumentIdentifier {
                     uri: filename.to_url()?,
                 },
-                options: FormattingOptions {
-                    tab_size,
-                    insert_spaces,
-                    properties: HashMap::new(),
-                },
+                options,
                 range: Range {
-                    start: Position {
-                        line: start_line,
-                        character: 0,
-                    },
-                    end: Position {
-                        line: end_line,
-                        character: 0,
-                    },
+                    start: self.get_lsp_position(&filename, start_line, start_col),
+                    end: self.get_lsp_position(&filename, end_line, end_col),
                 },
             },
         )?;
@@ -1545,6 +2432,77 @@ impl State {
         Ok(result)
     }
 
+    /// Characters which trigger on-type formatting, as announced by server.
+    fn get_on_type_formatting_triggers(&self, languageId: &str) -> Vec<String> {
+        let provider = self
+            .capabilities
+            .get(languageId)
+            .map(|c| c["capabilities"]["documentOnTypeFormattingProvider"].clone())
+            .unwrap_or_default();
+        let mut triggers: Vec<String> =
+            serde_json::from_value(provider["moreTriggerCharacter"].clone()).unwrap_or_default();
+        if let Some(first) = provider["firstTriggerCharacter"].as_str() {
+            triggers.insert(0, first.to_owned());
+        }
+        triggers
+    }
+
+    pub fn textDocument_onTypeFormatting(&mut self, params: &Value) -> Fallible<Value> {
+        self.textDocument_didChange(params)?;
+        info!("Begin {}", lsp::request::OnTypeFormatting::METHOD);
+        let (languageId, filename, line, character, handle, ch): (
+            String,
+            String,
+            u64,
+            u64,
+            bool,
+            String,
+        ) = self.gather_args(
+            &[
+                VimVar::LanguageId.to_key().as_str(),
+                VimVar::Filename.to_key().as_str(),
+                VimVar::Line.to_key().as_str(),
+                VimVar::Character.to_key().as_str(),
+                VimVar::Handle.to_key().as_str(),
+                "ch",
+            ],
+            params,
+        )?;
+
+        let options = self.get_formatting_options()?;
+        let position = self.get_lsp_position(&filename, line, character);
+        let result = self.call(
+            Some(&languageId),
+            lsp::request::OnTypeFormatting::METHOD,
+            DocumentOnTypeFormattingParams {
+                text_document: TextDocumentIdentifier {
+                    uri: filename.to_url()?,
+                },
+                position,
+                ch,
+                options,
+            },
+        )?;
+
+        if !handle {
+            return Ok(result);
+        }
+
+        let text_edits: Option<Vec<TextEdit>> = serde_json::from_value(result.clone())?;
+        let text_edits = text_edits.unwrap_or_default();
+        if text_edits.is_empty() {
+            return Ok(result);
+        }
+        let encoding = self.get_position_encoding(&languageId);
+        let position = get_cursor_after_TextEdits(position, &text_edits, encoding);
+        self.apply_TextEdits(&filename, &text_edits)?;
+        self.textDocument_didChange(&Value::Null)?;
+        let character = self.get_vim_character(&filename, &position);
+        self.cursor(position.line + 1, character + 1)?;
+        info!("End {}", lsp::request::OnTypeFormatting::METHOD);
+        Ok(result)
+    }
+
     pub fn completionItem_resolve(&mut self, params: &Value) -> Fallible<Value> {
         self.textDocument_didChange(params)?;
         info!("Begin {}", lsp::request::ResolveCompletionItem::METHOD);
@@ -1553,23 +2511,50 @@ impl State {
         let (completion_item,): (CompletionItem,) =
             self.gather_args(&["completionItem"], params)?;
 
-        let result = self.call(
-            Some(&languageId),
-            lsp::request::ResolveCompletionItem::METHOD,
-            completion_item,
-        )?;
+        let item = self.resolve_completion_item(&languageId, &completion_item)?;
+        let result = serde_json::to_value(&item)?;
 
         if !handle {
             return Ok(result);
         }
 
-        // TODO: proper integration.
-        let msg = format!("comletionItem/resolve result not handled: {:?}", result);
-        warn!("{}", msg);
-        self.echowarn(&msg)?;
+        if !item.to_display().is_empty() {
+            self.preview(&item)?;
+        }
 
         info!("End {}", lsp::request::ResolveCompletionItem::METHOD);
-        Ok(Value::Null)
+        Ok(result)
+    }
+
+    /// Resolve completion item, if supported by server.
+    ///
+    /// Results are cached until completion is done, so moving through the popup menu does not
+    /// request the same item again.
+    fn resolve_completion_item(
+        &mut self,
+        languageId: &str,
+        item: &CompletionItem,
+    ) -> Fallible<CompletionItem> {
+        let key = serde_json::to_string(item)?;
+        if let Some(resolved) = self.completion_resolved.get(&key) {
+            return Ok(resolved.clone());
+        }
+
+        let resolve_provider = self
+            .capabilities
+            .get(languageId)
+            .is_some_and(|c| c["capabilities"]["completionProvider"]["resolveProvider"] == true);
+        if !resolve_provider {
+            return Ok(item.clone());
+        }
+
+        let resolved: CompletionItem = self.call(
+            Some(languageId),
+            lsp::request::ResolveCompletionItem::METHOD,
+            item,
+        )?;
+        self.completion_resolved.insert(key, resolved.clone());
+        Ok(resolved)
     }
 
     pub fn workspace_symbol(&mut self, params: &Value) -> Fallible<Value> {
@@ -1599,13 +2584,15 @@ impl State {
                     .iter()
                     .map(|sym| {
                         let filename = sym.location.uri.filepath()?;
-                        let relpath = diff_paths(&filename, Path::new(&cwd)).unwrap_or(filename);
                         let start = sym.location.range.start;
+                        let character =
+                            self.get_vim_character(&filename.to_string_lossy(), &start);
+                        let relpath = diff_paths(&filename, Path::new(&cwd)).unwrap_or(filename);
                         Ok(format!(
                             "{}:{}:{}:\t{}\t\t{:?}",
                             relpath.to_string_lossy(),
                             start.line + 1,
-                            start.character + 1,
+                            character + 1,
                             sym.name,
                             sym.kind
                         ))
@@ -1619,13 +2606,19 @@ impl State {
                 )?;
             }
             SelectionUI::Quickfix => {
-                let list: Fallible<Vec<_>> = symbols.iter().map(QuickfixEntry::from_lsp).collect();
+                let list: Fallible<Vec<_>> = symbols
+                    .iter()
+                    .map(|sym| self.symbol_to_quickfix_entry(sym))
+                    .collect();
                 let list = list?;
                 self.setqflist(&list, " ", title)?;
                 self.echo("Workspace symbols populated to quickfix list.")?;
             }
             SelectionUI::LocationList => {
-                let list: Fallible<Vec<_>> = symbols.iter().map(QuickfixEntry::from_lsp).collect();
+                let list: Fallible<Vec<_>> = symbols
+                    .iter()
+                    .map(|sym| self.symbol_to_quickfix_entry(sym))
+                    .collect();
                 let list = list?;
                 self.setloclist(&list, " ", title)?;
                 self.echo("Workspace symbols populated to location list.")?;
@@ -1642,15 +2635,26 @@ impl State {
         let (command, arguments): (String, Vec<Value>) =
             self.gather_args(&["command", "arguments"], params)?;
 
-        let result = self.call(
-            Some(&languageId),
-            lsp::request::ExecuteCommand::METHOD,
-            ExecuteCommandParams { command, arguments },
-        )?;
+        let result = self.execute_command(&languageId, command, arguments, None)?;
         info!("End {}", lsp::request::ExecuteCommand::METHOD);
         Ok(result)
     }
 
+    fn execute_command(
+        &mut self,
+        languageId: &str,
+        command: String,
+        arguments: Vec<Value>,
+        deadline: Option<Instant>,
+    ) -> Fallible<Value> {
+        self.call_until(
+            Some(languageId),
+            lsp::request::ExecuteCommand::METHOD,
+            ExecuteCommandParams { command, arguments },
+            deadline,
+        )
+    }
+
     pub fn workspace_applyEdit(&mut self, params: &Value) -> Fallible<Value> {
         info!("Begin {}", lsp::request::ApplyWorkspaceEdit::METHOD);
 
@@ -1847,17 +2851,28 @@ impl State {
         // Unify name to avoid mismatch due to case insensitivity.
         let filename = filename.canonicalize();
 
-        let diagnostics = params.diagnostics;
+        let diagnostics =
+            filter_diagnostics(&self.diagnosticsFilter, &filename, &params.diagnostics);
+        let summary_prev =
+            get_diagnostics_summary(self.diagnostics.get(&filename).unwrap_or(&vec![]));
+        let summary = get_diagnostics_summary(&diagnostics);
 
+        self.diagnostics_published
+            .insert(filename.clone(), params.diagnostics);
         self.diagnostics
             .insert(filename.clone(), diagnostics.clone());
-        self.update_quickfixlist()?;
+        self.update_quickfixlist(std::slice::from_ref(&filename))?;
+        if summary != summary_prev {
+            self.update_diagnostics_summary(std::slice::from_ref(&filename))?;
+            self.notify(None, "s:ExecuteAutocmd", "LanguageClientDiagnosticsSummaryChanged")?;
+        }
 
         let current_filename: String = self.eval(VimVar::Filename)?;
         if filename != current_filename.canonicalize() {
             return Ok(());
         }
         self.process_diagnostics(&current_filename, &diagnostics)?;
+        self.update_diagnostics_matches(&current_filename, &diagnostics)?;
         self.languageClient_handleCursorMoved(&Value::Null)?;
         self.notify(None, "s:ExecuteAutocmd", "LanguageClientDiagnosticsChanged")?;
 
@@ -2035,11 +3050,74 @@ impl State {
         info!("Begin {}", REQUEST__SetDiagnosticsList);
         let (diagnosticsList,): (DiagnosticsList,) =
             self.gather_args(&["diagnosticsList"], params)?;
+        if diagnosticsList != self.diagnosticsList {
+            if let DiagnosticsList::Buffer = self.diagnosticsList {
+                self.notify(None, "s:ClearBufferDiagnosticsLists", json!([]))?;
+            }
+            // Lists filled in previous mode would be left stale.
+            self.notify(None, "s:ClearDiagnosticsLists", json!([DiagnosticsListTitle]))?;
+        }
         self.diagnosticsList = diagnosticsList;
+        let filenames: Vec<_> = self.diagnostics.keys().cloned().collect();
+        self.update_quickfixlist(&filenames)?;
         info!("End {}", REQUEST__SetDiagnosticsList);
         Ok(Value::Null)
     }
 
+    pub fn languageClient_toggleDiagnosticsFilter(&mut self, params: &Value) -> Fallible<Value> {
+        info!("Begin {}", REQUEST__ToggleDiagnosticsFilter);
+        let (filter, enable): (String, Option<u64>) =
+            self.gather_args(&[("filter", "v:null"), ("enable", "v:null")], params)?;
+        let kind = DiagnosticsFilterKind::from_str(&filter)?;
+        let enable = match enable {
+            Some(enable) => enable == 1,
+            None => !self.diagnosticsFilter.is_enabled(kind),
+        };
+        if enable {
+            self.diagnosticsFilter.disabled.remove(&kind);
+        } else {
+            self.diagnosticsFilter.disabled.insert(kind);
+        }
+
+        let summaries = |diagnostics: &HashMap<String, Vec<Diagnostic>>| -> HashMap<_, _> {
+            diagnostics
+                .iter()
+                .map(|(f, dns)| (f.clone(), get_diagnostics_summary(dns)))
+                .collect()
+        };
+        let summaries_prev = summaries(&self.diagnostics);
+
+        // Re-apply filters to diagnostics already published.
+        let filter = self.diagnosticsFilter.clone();
+        self.diagnostics = self
+            .diagnostics_published
+            .iter()
+            .map(|(f, dns)| (f.clone(), filter_diagnostics(&filter, f, dns)))
+            .collect();
+        let filenames: Vec<_> = self.diagnostics.keys().cloned().collect();
+        self.update_quickfixlist(&filenames)?;
+        if summaries(&self.diagnostics) != summaries_prev {
+            self.update_diagnostics_summary(&filenames)?;
+            self.notify(None, "s:ExecuteAutocmd", "LanguageClientDiagnosticsSummaryChanged")?;
+        }
+
+        let current_filename: String = self.eval(VimVar::Filename)?;
+        let current_filename = current_filename.canonicalize();
+        for (f, diagnostics) in self.diagnostics.clone() {
+            self.process_diagnostics(&f, &diagnostics)?;
+            if f == current_filename {
+                self.update_diagnostics_matches(&f, &diagnostics)?;
+                self.languageClient_handleCursorMoved(&Value::Null)?;
+            } else {
+                self.render_diagnostics_in_buffer(&f)?;
+            }
+        }
+        self.notify(None, "s:ExecuteAutocmd", "LanguageClientDiagnosticsChanged")?;
+
+        info!("End {}", REQUEST__ToggleDiagnosticsFilter);
+        Ok(json!(enable))
+    }
+
     pub fn languageClient_registerHandlers(&mut self, params: &Value) -> Fallible<Value> {
         info!("Begin {}", REQUEST__RegisterHandlers);
         let handlers: Fallible<HashMap<String, String>> = params
@@ -2067,34 +3145,119 @@ impl State {
 
     pub fn languageClient_omniComplete(&mut self, params: &Value) -> Fallible<Value> {
         info!("Begin {}", REQUEST__OmniComplete);
-        let result = self.textDocument_completion(params)?;
-        let result: Option<CompletionResponse> = serde_json::from_value(result)?;
-        let result = result.unwrap_or_else(|| CompletionResponse::Array(vec![]));
-        let mut matches = match result {
-            CompletionResponse::Array(arr) => arr,
-            CompletionResponse::List(list) => list.items,
+        let (filename, line, complete_position, base): (String, u64, Option<u64>, String) = self
+            .gather_args(
+                &[
+                    (VimVar::Filename.to_key().as_str(), "LSP#filename()"),
+                    (VimVar::Line.to_key().as_str(), "LSP#line()"),
+                    ("complete_position", "v:null"),
+                    ("base", "''"),
+                ],
+                params,
+            )?;
+
+        // Line text before the word being completed, to tell whether word boundary moved, and
+        // hash of other lines, to tell whether document was edited elsewhere.
+        self.textDocument_didChange(params)?;
+        let context = complete_position.and_then(|complete_position| {
+            let (text, _) = self.get_text_document_line(&filename, line)?;
+            let prefix = text.get(..complete_position as usize)?.to_owned();
+            let document = self.text_documents.get(&filename)?;
+            Some((prefix, hash_other_lines(&document.text, line)))
+        });
+
+        let cached = match (self.completion_sessions.get(&filename), &context) {
+            (Some(session), Some((prefix, document_hash)))
+                if session.is_reusable(line, prefix, &base, *document_hash) =>
+            {
+                Some(session.items.clone())
+            }
+            _ => None,
+        };
+        let matches = if let Some(items) = cached {
+            info!("Reuse completion items of {}", filename);
+            items
+        } else {
+            let result = self.textDocument_completion(params)?;
+            let result: Option<CompletionResponse> = serde_json::from_value(result)?;
+            let result = result.unwrap_or_else(|| CompletionResponse::Array(vec![]));
+            let (items, is_incomplete) = match result {
+                CompletionResponse::Array(arr) => (arr, false),
+                CompletionResponse::List(list) => (list.items, list.is_incomplete),
+            };
+            match context {
+                Some((prefix, document_hash)) => {
+                    let session = CompletionSession {
+                        line,
+                        prefix,
+                        base: base.clone(),
+                        document_hash,
+                        items: items.clone(),
+                        is_incomplete,
+                    };
+                    self.completion_sessions.insert(filename.clone(), session);
+                }
+                None => {
+                    self.completion_sessions.remove(&filename);
+                }
+            }
+            items
         };
-        if !matches.iter().any(|m| m.sort_text.is_none()) {
-            matches.sort_by(|m1, m2| {
-                m1.sort_text
-                    .as_ref()
-                    .unwrap()
-                    .cmp(m2.sort_text.as_ref().unwrap())
-            });
-        }
 
-        let (complete_position,): (Option<u64>,) =
-            self.gather_args(&[("complete_position", "v:null")], params)?;
+        let matches = rank_completion_items(matches, &base, self.completionDropDeprecated);
+        let complete_position = complete_position
+            .map(|character| self.get_lsp_position(&filename, line, character).character);
 
+        let (languageId,): (String,) = self.gather_args(&[VimVar::LanguageId], params)?;
+        let encoding = self.get_position_encoding(&languageId);
+        let format = self.get_completion_format(&languageId);
         let matches: Fallible<Vec<VimCompleteItem>> = matches
             .iter()
-            .map(|item| VimCompleteItem::from_lsp(item, complete_position))
+            .map(|item| VimCompleteItem::from_lsp(item, complete_position, encoding, &format))
             .collect();
         let matches = matches?;
         info!("End {}", REQUEST__OmniComplete);
         Ok(serde_json::to_value(matches)?)
     }
 
+    /// Completion items for auto-completion while typing, or null if completion should not be
+    /// triggered.
+    ///
+    /// Completion is triggered when some part of a word was typed (`base`), or when `typed` is
+    /// one of the trigger characters announced by server.
+    pub fn languageClient_autoComplete(&mut self, params: &Value) -> Fallible<Value> {
+        info!("Begin {}", REQUEST__AutoComplete);
+        let (languageId,): (String,) = self.gather_args(&[VimVar::LanguageId], params)?;
+        let (typed, base): (String, String) =
+            self.gather_args(&[("typed", "''"), ("base", "''")], params)?;
+        if !self.serverCommands.contains_key(&languageId) {
+            return Ok(Value::Null);
+        }
+
+        let trigger_characters: Vec<String> = self
+            .capabilities
+            .get(&languageId)
+            .and_then(|c| {
+                let characters = &c["capabilities"]["completionProvider"]["triggerCharacters"];
+                serde_json::from_value(characters.clone()).ok()
+            }).unwrap_or_default();
+        let trigger_character = if !base.is_empty() {
+            None
+        } else if trigger_characters.contains(&typed) {
+            Some(typed)
+        } else {
+            return Ok(Value::Null);
+        };
+
+        let result = self.languageClient_omniComplete(
+            &json!({
+                "triggerCharacter": trigger_character,
+            }).combine(params),
+        )?;
+        info!("End {}", REQUEST__AutoComplete);
+        Ok(result)
+    }
+
     pub fn languageClient_handleBufNewFile(&mut self, params: &Value) -> Fallible<()> {
         info!("Begin {}", NOTIFICATION__HandleBufNewFile);
         let (filename,): (String,) = self.gather_args(&[VimVar::Filename], params)?;
@@ -2129,6 +3292,7 @@ impl State {
 
             if let Some(diagnostics) = self.diagnostics.get(&filename).cloned() {
                 self.process_diagnostics(&filename, &diagnostics)?;
+                self.update_diagnostics_matches(&filename, &diagnostics)?;
                 self.languageClient_handleCursorMoved(params)?;
             }
         } else {
@@ -2154,6 +3318,19 @@ impl State {
             return Ok(());
         }
 
+        // Character just inserted, if any.
+        let (typed,): (String,) = self.gather_args(&[("typed", "''")], params)?;
+
+        if !typed.is_empty() && self.get_on_type_formatting_triggers(&languageId).contains(&typed) {
+            self.textDocument_onTypeFormatting(&json!({
+                "languageId": languageId,
+                "filename": filename,
+                "ch": typed,
+            }).combine(params))?;
+            info!("End {}", NOTIFICATION__HandleTextChanged);
+            return Ok(());
+        }
+
         let skip_notification = self.get(|state| {
             if let Some(metadata) = state.text_documents_metadata.get(&filename) {
                 if let Some(throttle) = state.change_throttle {
@@ -2169,11 +3346,39 @@ impl State {
             return Ok(());
         }
 
+        // Show signature help on trigger characters, and keep it updated while typing arguments.
+        // Document is synced by the request.
+        if self.autoSignatureHelp
+            && !typed.is_empty()
+            && (self.signature_help.contains_key(&filename)
+                || self.get_signature_help_triggers(&languageId).contains(&typed))
+        {
+            self.textDocument_signatureHelp(&json!({
+                "languageId": languageId,
+                "filename": filename,
+            }).combine(params))?;
+            info!("End {}", NOTIFICATION__HandleTextChanged);
+            return Ok(());
+        }
+
         self.textDocument_didChange(params)?;
         info!("End {}", NOTIFICATION__HandleTextChanged);
         Ok(())
     }
 
+    /// Forget signature help of buffer once insert mode is entered or left, as it is no longer
+    /// shown.
+    pub fn languageClient_handleInsertModeChanged(&mut self, params: &Value) -> Fallible<()> {
+        info!("Begin {}", NOTIFICATION__HandleInsertModeChanged);
+        let (filename,): (String,) = self.gather_args(&[VimVar::Filename], params)?;
+        self.update(|state| {
+            state.signature_help.remove(&filename);
+            Ok(())
+        })?;
+        info!("End {}", NOTIFICATION__HandleInsertModeChanged);
+        Ok(())
+    }
+
     pub fn languageClient_handleBufWritePost(&mut self, params: &Value) -> Fallible<()> {
         info!("Begin {}", NOTIFICATION__HandleBufWritePost);
         self.textDocument_didSave(params)?;
@@ -2191,9 +3396,14 @@ impl State {
 
         self.update(|state| {
             state.text_documents.retain(|f, _| f != &filename);
+            state.diagnostics_published.retain(|f, _| f != &filename);
             state.diagnostics.retain(|f, _| f != &filename);
             state.line_diagnostics.retain(|fl, _| fl.0 != filename);
             state.signs.retain(|f, _| f != &filename);
+            state.virtual_texts.retain(|f, _| f != &filename);
+            state.virtual_texts_placed.retain(|f, _| f != &filename);
+            state.completion_sessions.remove(&filename);
+            state.signature_help.remove(&filename);
             Ok(())
         })?;
         self.textDocument_didClose(params)?;
@@ -2292,24 +3502,53 @@ impl State {
             self.notify(
                 None,
                 "nvim_buf_clear_highlight",
-                json!([0, source, visible_line_start, visible_line_end]),
+                json!([0, source, visible_line_start, visible_line_end + 1]),
             )?;
 
             self.notify(None, "s:AddHighlights", json!([source, highlights]))?;
         }
 
+        if self.useVirtualText {
+            let virtual_texts: Vec<_> = self
+                .virtual_texts
+                .get(&filename)
+                .cloned()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|vt| vt.line >= visible_line_start && vt.line <= visible_line_end)
+                .collect();
+
+            if Some(&virtual_texts) != self.virtual_texts_placed.get(&filename) {
+                let namespace = self.get_virtual_text_namespace()?;
+                self.notify(
+                    None,
+                    "s:SetVirtualTexts",
+                    json!([
+                        namespace,
+                        visible_line_start,
+                        visible_line_end + 1,
+                        virtual_texts
+                    ]),
+                )?;
+                self.virtual_texts_placed
+                    .insert(filename.clone(), virtual_texts);
+            }
+        }
+
         info!("End {}", NOTIFICATION__HandleCursorMoved);
         Ok(())
     }
 
     pub fn languageClient_handleCompleteDone(&mut self, params: &Value) -> Fallible<()> {
-        let (filename, completed_item, line, character): (
+        let (languageId, filename, completed_item, line, character): (
+            String,
             String,
             VimCompleteItem,
             u64,
             u64,
         ) = self.gather_args(
             &[
+                VimVar::LanguageId.to_key().as_str(),
                 VimVar::Filename.to_key().as_str(),
                 "completed_item",
                 VimVar::Line.to_key().as_str(),
@@ -2318,33 +3557,148 @@ impl State {
             params,
         )?;
 
-        let user_data = match completed_item.user_data {
-            Some(user_data) => user_data,
-            _ => return Ok(()),
-        };
-        let user_data: VimCompleteItemUserData = serde_json::from_str(&user_data)?;
-        let lspitem = match user_data.lspitem {
+        let lspitem = match completed_item.lspitem()? {
             Some(lspitem) => lspitem,
             _ => return Ok(()),
         };
 
+        // Some servers only send additional edits, e.g., auto imports, on resolve.
+        let lspitem = if lspitem.additional_text_edits.is_none() {
+            self.resolve_completion_item(&languageId, &lspitem)
+                .unwrap_or_else(|err| {
+                    warn!("Failed to resolve completion item: {}", err);
+                    lspitem
+                })
+        } else {
+            lspitem
+        };
+        self.completion_resolved.clear();
+
+        // Expand snippet with built-in engine, unless a snippet plugin takes care of it.
+        let snippet = match completed_item.snippet {
+            Some(ref snippet) if completed_item.is_snippet == Some(true) => {
+                if self.eval::<_, u64>("s:hasSnippetPlugin()")? == 1 {
+                    None
+                } else {
+                    Some(snippet.clone())
+                }
+            }
+            _ => None,
+        };
+
         let mut edits = vec![];
+        let mut text_edit_applied = false;
         if self.completionPreferTextEdit {
-            if let Some(edit) = lspitem.text_edit {
+            if let Some(mut edit) = lspitem.text_edit {
+                if let Some(ref snippet) = snippet {
+                    // No tabstops in this case, only plain text.
+                    edit.new_text = Snippet::parse(snippet, |_| None).text;
+                }
                 self.command("undo")?;
                 edits.push(edit.clone());
+                text_edit_applied = true;
             };
         }
-        if let Some(aedits) = lspitem.additional_text_edits {
-            edits.extend(aedits.clone());
+        let aedits = lspitem.additional_text_edits.unwrap_or_default();
+        edits.extend(aedits.clone());
+
+        if !edits.is_empty() {
+            // Keep cursor after completed text when lines are added or removed above.
+            let encoding = self.get_position_encoding(&languageId);
+            let position = self.get_lsp_position(&filename, line, character);
+            let position = get_cursor_after_TextEdits(position, &aedits, encoding);
+            self.apply_TextEdits(&filename, &edits)?;
+            self.textDocument_didChange(&Value::Null)?;
+            let character = self.get_vim_character(&filename, &position);
+            self.cursor(position.line + 1, character + 1)?;
+        }
+
+        match snippet {
+            Some(ref snippet) if !text_edit_applied => {
+                self.expand_snippet(&filename, &completed_item.word, snippet)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Replace completed `word` before cursor with expanded `snippet`, then jump to its first
+    /// tabstop.
+    fn expand_snippet(&mut self, filename: &str, word: &str, snippet: &str) -> Fallible<()> {
+        let (lnum, col, line_text): (u64, usize, String) =
+            self.eval(["line('.')", "col('.')", "getline('.')"].as_ref())?;
+        let end = col - 1;
+        let start = match end.checked_sub(word.len()) {
+            Some(start) if line_text.get(start..end) == Some(word) => start,
+            _ => {
+                warn!("Completed word not found before cursor, snippet not expanded");
+                return Ok(());
+            }
         };
 
-        if edits.is_empty() {
+        let snippet = Snippet::parse(snippet, |name| {
+            get_snippet_variable(name, filename, lnum - 1, &line_text)
+        });
+        let indent: String = line_text
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .collect();
+        let (mut lines, positions) = snippet.layout(&indent);
+        lines[0].insert_str(0, &line_text[..start]);
+        if let Some(last) = lines.last_mut() {
+            last.push_str(&line_text[end..]);
+        }
+        self.set_lines((lnum - 1) as usize, lnum as usize, &lines)?;
+
+        // Tabstops as lists of [lnum, col, len], 1-based.
+        let tabstops: Vec<Vec<[u64; 3]>> = positions
+            .iter()
+            .map(|ranges| {
+                ranges
+                    .iter()
+                    .map(|p| {
+                        let col = if p.line == 0 { start as u64 + p.col } else { p.col };
+                        [lnum + p.line, col + 1, p.len]
+                    }).collect()
+            }).collect();
+        let choices: Vec<_> = snippet.tabstops.iter().map(|t| t.choices.clone()).collect();
+        self.notify(None, "s:StartSnippet", json!([tabstops, choices]))?;
+        self.textDocument_didChange(&Value::Null)
+    }
+
+    /// Show documentation of selected completion item, resolving it if necessary.
+    pub fn languageClient_handleCompleteChanged(&mut self, params: &Value) -> Fallible<()> {
+        info!("Begin {}", NOTIFICATION__HandleCompleteChanged);
+        let (languageId, completed_item, selected): (String, VimCompleteItem, i64) = self
+            .gather_args(
+                &[
+                    VimVar::LanguageId.to_key().as_str(),
+                    "completed_item",
+                    "selected",
+                ],
+                params,
+            )?;
+        if !self.serverCommands.contains_key(&languageId) {
             return Ok(());
         }
 
-        self.apply_TextEdits(filename, &edits)?;
-        self.cursor(line + 1, character + 1)
+        let lspitem = match completed_item.lspitem()? {
+            Some(ref lspitem) if lspitem.documentation.is_none() => lspitem.clone(),
+            _ => return Ok(()),
+        };
+        let item = self.resolve_completion_item(&languageId, &lspitem)?;
+        if item.documentation.is_some() {
+            let encoding = self.get_position_encoding(&languageId);
+            let format = self.get_completion_format(&languageId);
+            let info = VimCompleteItem::from_lsp(&item, None, encoding, &format)?.info;
+            self.notify(
+                None,
+                "s:ShowCompletionItemDocumentation",
+                json!([selected, info.to_display()]),
+            )?;
+        }
+
+        info!("End {}", NOTIFICATION__HandleCompleteChanged);
+        Ok(())
     }
 
     pub fn languageClient_FZFSinkLocation(&mut self, params: &Value) -> Fallible<()> {
@@ -2401,37 +3755,30 @@ impl State {
         info!("Begin {}", NOTIFICATION__FZFSinkCommand);
         let (selection,): (String,) = self.gather_args(&["selection"], params)?;
         let tokens: Vec<&str> = selection.splitn(2, ": ").collect();
-        let command = tokens
+        let label = tokens
             .get(0)
             .cloned()
-            .ok_or_else(|| format_err!("Failed to get command! tokens: {:?}", tokens))?;
+            .ok_or_else(|| format_err!("Failed to get label! tokens: {:?}", tokens))?;
         let title = tokens
             .get(1)
             .cloned()
             .ok_or_else(|| format_err!("Failed to get title! tokens: {:?}", tokens))?;
         let entry = self.get(|state| {
-            let commands = &state.stashed_codeAction_commands;
+            let actions = &state.stashed_codeActions;
 
-            commands
+            actions
                 .iter()
-                .find(|e| e.command == command && e.title == title)
+                .find(|e| e.label() == label && e.title() == title)
                 .cloned()
                 .ok_or_else(|| {
-                    format_err!("No stashed command found! stashed commands: {:?}", commands)
+                    format_err!("No stashed code action found! stashed actions: {:?}", actions)
                 })
         })?;
 
-        if self.try_handle_command_by_client(&entry)? {
-            return Ok(());
-        }
-
-        self.workspace_executeCommand(&json!({
-                "command": entry.command,
-                "arguments": entry.arguments,
-            }))?;
+        self.execute_code_action(&entry, None)?;
 
         self.update(|state| {
-            state.stashed_codeAction_commands = vec![];
+            state.stashed_codeActions = vec![];
             Ok(())
         })?;
 
@@ -2464,12 +3811,17 @@ impl State {
             CompletionResponse::Array(_) => false,
             CompletionResponse::List(ref list) => list.is_incomplete,
         };
-        let matches: Fallible<Vec<VimCompleteItem>> = match result {
+        let items = match result {
             CompletionResponse::Array(arr) => arr,
             CompletionResponse::List(list) => list.items,
-        }.iter()
-        .map(|item| VimCompleteItem::from_lsp(item, None))
-        .collect();
+        };
+        let encoding = self.get_position_encoding(&ctx.filetype);
+        let format = self.get_completion_format(&ctx.filetype);
+        let matches: Fallible<Vec<VimCompleteItem>> =
+            rank_completion_items(items, &ctx.base, self.completionDropDeprecated)
+                .iter()
+                .map(|item| VimCompleteItem::from_lsp(item, None, encoding, &format))
+                .collect();
         let matches = matches?;
         self.call::<_, u8>(
             None,
@@ -2509,12 +3861,17 @@ impl State {
                 CompletionResponse::List(ref list) => list.is_incomplete,
                 _ => false,
             };
-            let matches_result: Fallible<Vec<VimCompleteItem>> = match completion {
+            let items = match completion {
                 CompletionResponse::Array(arr) => arr,
                 CompletionResponse::List(list) => list.items,
-            }.iter()
-            .map(|item| VimCompleteItem::from_lsp(item, None))
-            .collect();
+            };
+            let encoding = self.get_position_encoding(&ctx.filetype);
+            let format = self.get_completion_format(&ctx.filetype);
+            let matches_result: Fallible<Vec<VimCompleteItem>> =
+                rank_completion_items(items, &ctx.base, self.completionDropDeprecated)
+                    .iter()
+                    .map(|item| VimCompleteItem::from_lsp(item, None, encoding, &format))
+                    .collect();
             matches = matches_result?;
         } else {
             is_incomplete = true;
@@ -2529,19 +3886,21 @@ impl State {
         result
     }
 
-    pub fn languageClient_explainErrorAtPoint(&mut self, params: &Value) -> Fallible<Value> {
-        info!("Begin {}", REQUEST__ExplainErrorAtPoint);
+    fn get_diagnostic_at_point(&mut self, params: &Value) -> Fallible<Diagnostic> {
         let (filename, line, character): (String, u64, u64) =
             self.gather_args(&[VimVar::Filename, VimVar::Line, VimVar::Character], params)?;
-        let diag = self.get(|state| {
+        let position = self.get_lsp_position(&filename, line, character);
+        self.get(|state| {
             state
                 .diagnostics
                 .get(&filename)
                 .ok_or_else(|| format_err!("No diagnostics found: filename: {}", filename,))?
                 .iter()
                 .find(|d| {
-                    (line, character) >= (d.range.start.line, d.range.start.character)
-                        && (line, character) < (d.range.end.line, d.range.end.character)
+                    (position.line, position.character)
+                        >= (d.range.start.line, d.range.start.character)
+                        && (position.line, position.character)
+                            < (d.range.end.line, d.range.end.character)
                 }).cloned()
                 .ok_or_else(|| {
                     format_err!(
@@ -2551,13 +3910,137 @@ impl State {
                         character
                     )
                 })
-        })?;
-        self.preview(diag.message.as_str())?;
+        })
+    }
+
+    fn related_information_to_quickfix_entries(
+        &mut self,
+        languageId: &str,
+        diag: &Diagnostic,
+    ) -> Fallible<Vec<QuickfixEntry>> {
+        let encoding = self.get_position_encoding(languageId);
+        let mut entries = vec![];
+        for info in diag.related_information.as_ref().unwrap_or(&vec![]) {
+            let filename = info.location.uri.filepath()?.to_string_lossy().into_owned();
+            let start = info.location.range.start;
+            let text = self.get_line(&filename, start.line).unwrap_or_default();
+            let character = character_to_byte(&text, start.character, encoding);
+            entries.push(QuickfixEntry {
+                filename,
+                lnum: start.line + 1,
+                col: Some(character as u64 + 1),
+                text: Some(info.message.clone()),
+                nr: None,
+                typ: None,
+            });
+        }
+        Ok(entries)
+    }
+
+    pub fn languageClient_explainErrorAtPoint(&mut self, params: &Value) -> Fallible<Value> {
+        info!("Begin {}", REQUEST__ExplainErrorAtPoint);
+        let (languageId,): (String,) = self.gather_args(&[VimVar::LanguageId], params)?;
+        let diag = self.get_diagnostic_at_point(params)?;
+        let related = self.related_information_to_quickfix_entries(&languageId, &diag)?;
+        let cwd: String = self.eval("getcwd()")?;
+        let explanation = get_diagnostic_explanation(&diag, &related, &cwd);
+        self.preview(explanation.as_str())?;
 
         info!("End {}", REQUEST__ExplainErrorAtPoint);
         Ok(Value::Null)
     }
 
+    pub fn languageClient_diagnosticRelatedLocations(&mut self, params: &Value) -> Fallible<Value> {
+        info!("Begin {}", REQUEST__DiagnosticRelatedLocations);
+        let (languageId,): (String,) = self.gather_args(&[VimVar::LanguageId], params)?;
+        let diag = self.get_diagnostic_at_point(params)?;
+        let list = self.related_information_to_quickfix_entries(&languageId, &diag)?;
+        if list.is_empty() {
+            self.echowarn("No related information found")?;
+        } else {
+            self.setloclist(&list, " ", "[LC]: related information")?;
+            self.echo("Location list updated.")?;
+        }
+
+        info!("End {}", REQUEST__DiagnosticRelatedLocations);
+        Ok(Value::Null)
+    }
+
+    pub fn languageClient_diagnosticsSummary(&mut self, params: &Value) -> Fallible<Value> {
+        info!("Begin {}", REQUEST__DiagnosticsSummary);
+        let (languageId, filename): (String, String) =
+            self.gather_args(&[VimVar::LanguageId, VimVar::Filename], params)?;
+        // Unify filename.
+        let filename = filename.canonicalize();
+        let buffer = get_diagnostics_summary(self.diagnostics.get(&filename).unwrap_or(&vec![]));
+        let workspace = match self.roots.get(&languageId) {
+            Some(root) => get_diagnostics_summary(
+                self.diagnostics
+                    .iter()
+                    .filter(|(f, _)| f.starts_with(root))
+                    .flat_map(|(_, dns)| dns),
+            ),
+            None => DiagnosticsSummary::default(),
+        };
+
+        info!("End {}", REQUEST__DiagnosticsSummary);
+        Ok(json!({
+            "buffer": buffer,
+            "workspace": workspace,
+        }))
+    }
+
+    pub fn languageClient_diagnosticNext(&mut self, params: &Value) -> Fallible<Value> {
+        info!("Begin {}", REQUEST__DiagnosticNext);
+        self.goto_diagnostic(params, true)?;
+        info!("End {}", REQUEST__DiagnosticNext);
+        Ok(Value::Null)
+    }
+
+    pub fn languageClient_diagnosticPrev(&mut self, params: &Value) -> Fallible<Value> {
+        info!("Begin {}", REQUEST__DiagnosticPrev);
+        self.goto_diagnostic(params, false)?;
+        info!("End {}", REQUEST__DiagnosticPrev);
+        Ok(Value::Null)
+    }
+
+    /// Move cursor to next/previous diagnostic of current file, wrapping around.
+    fn goto_diagnostic(&mut self, params: &Value, forward: bool) -> Fallible<()> {
+        let (filename, line, character): (String, u64, u64) =
+            self.gather_args(&[VimVar::Filename, VimVar::Line, VimVar::Character], params)?;
+        let (severity,): (Option<String>,) = self.gather_args(&[("severity", "v:null")], params)?;
+        // Unify filename.
+        let filename = filename.canonicalize();
+        let severity = match severity {
+            Some(s) => Some(
+                parse_diagnostic_severity(&s)
+                    .ok_or_else(|| format_err!("Invalid severity: {}", s))?,
+            ),
+            None => None,
+        };
+
+        let position = self.get_lsp_position(&filename, line, character);
+        let diagnostics = self.diagnostics.get(&filename).cloned().unwrap_or_default();
+        let dn = match find_next_diagnostic(&diagnostics, &position, severity, forward) {
+            Some(dn) => dn.clone(),
+            None => {
+                self.echowarn("No diagnostics found")?;
+                return Ok(());
+            }
+        };
+
+        let character = self.get_vim_character(&filename, &dn.range.start);
+        self.command("normal! m'")?;
+        self.cursor(dn.range.start.line + 1, character + 1)?;
+
+        let message = get_diagnostic_message(&dn);
+        self.echo_ellipsis(&message)?;
+        // Prevent cursor moved handler from echoing another diagnostic of the same line.
+        self.last_cursor_line = dn.range.start.line;
+        self.last_line_diagnostic = message;
+        Ok(())
+    }
+
     // Extensions by language servers.
     pub fn language_status(&mut self, params: &Value) -> Fallible<()> {
         info!("Begin {}", NOTIFICATION__LanguageStatus);
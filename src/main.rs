@@ -1,9 +1,11 @@
 #![allow(non_snake_case, non_upper_case_globals, unknown_lints)]
 
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::Into;
 use std::fmt::Debug;
 use std::fs::{read_to_string, File};
+use std::hash::{Hash, Hasher};
 use std::io::prelude::*;
 use std::io::{BufRead, BufReader, BufWriter};
 use std::net::TcpStream;
@@ -27,6 +29,9 @@ use failure::{err_msg, Error, ResultExt};
 extern crate maplit;
 use maplit::hashmap;
 
+#[macro_use]
+extern crate lazy_static;
+
 extern crate serde;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -65,6 +70,8 @@ mod types;
 use crate::types::*;
 mod utils;
 use crate::utils::*;
+mod snippet;
+use crate::snippet::*;
 mod languageclient;
 mod logger;
 mod rpchandler;
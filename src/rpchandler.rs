@@ -41,6 +41,7 @@ impl State {
             lsp::request::References::METHOD => self.textDocument_references(&params),
             lsp::request::Formatting::METHOD => self.textDocument_formatting(&params),
             lsp::request::RangeFormatting::METHOD => self.textDocument_rangeFormatting(&params),
+            lsp::request::OnTypeFormatting::METHOD => self.textDocument_onTypeFormatting(&params),
             lsp::request::ResolveCompletionItem::METHOD => self.completionItem_resolve(&params),
             lsp::request::ExecuteCommand::METHOD => self.workspace_executeCommand(&params),
             lsp::request::ApplyWorkspaceEdit::METHOD => self.workspace_applyEdit(&params),
@@ -54,11 +55,25 @@ impl State {
             REQUEST__RegisterServerCommands => self.languageClient_registerServerCommands(&params),
             REQUEST__SetLoggingLevel => self.languageClient_setLoggingLevel(&params),
             REQUEST__SetDiagnosticsList => self.languageClient_setDiagnosticsList(&params),
+            REQUEST__ToggleDiagnosticsFilter => {
+                self.languageClient_toggleDiagnosticsFilter(&params)
+            }
             REQUEST__RegisterHandlers => self.languageClient_registerHandlers(&params),
             REQUEST__NCMRefresh => self.NCM_refresh(&params),
             REQUEST__NCM2OnComplete => self.NCM2_on_complete(&params),
+            REQUEST__QuickFix => self.languageClient_quickFix(&params),
+            REQUEST__CodeActionsOnSave => self.languageClient_codeActionsOnSave(&params),
+            REQUEST__FormatOnSave => self.languageClient_formatOnSave(&params),
+            REQUEST__DiagnosticsSummary => self.languageClient_diagnosticsSummary(&params),
+            REQUEST__DiagnosticNext => self.languageClient_diagnosticNext(&params),
+            REQUEST__DiagnosticPrev => self.languageClient_diagnosticPrev(&params),
+            REQUEST__CycleSignature => self.languageClient_cycleSignature(&params),
             REQUEST__ExplainErrorAtPoint => self.languageClient_explainErrorAtPoint(&params),
+            REQUEST__DiagnosticRelatedLocations => {
+                self.languageClient_diagnosticRelatedLocations(&params)
+            }
             REQUEST__OmniComplete => self.languageClient_omniComplete(&params),
+            REQUEST__AutoComplete => self.languageClient_autoComplete(&params),
             REQUEST__ClassFileContents => self.java_classFileContents(&params),
             REQUEST__DebugInfo => self.debug_info(&params),
 
@@ -133,6 +148,12 @@ impl State {
             NOTIFICATION__HandleBufDelete => self.languageClient_handleBufDelete(&params)?,
             NOTIFICATION__HandleCursorMoved => self.languageClient_handleCursorMoved(&params)?,
             NOTIFICATION__HandleCompleteDone => self.languageClient_handleCompleteDone(&params)?,
+            NOTIFICATION__HandleCompleteChanged => {
+                self.languageClient_handleCompleteChanged(&params)?
+            }
+            NOTIFICATION__HandleInsertModeChanged => {
+                self.languageClient_handleInsertModeChanged(&params)?
+            }
             NOTIFICATION__FZFSinkLocation => self.languageClient_FZFSinkLocation(&params)?,
             NOTIFICATION__FZFSinkCommand => self.languageClient_FZFSinkCommand(&params)?,
             NOTIFICATION__ClearDocumentHighlight => {
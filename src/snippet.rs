@@ -0,0 +1,410 @@
+use super::*;
+use std::collections::BTreeMap;
+
+/// A snippet in LSP snippet syntax, expanded to plain text.
+///
+/// See https://microsoft.github.io/language-server-protocol/specification#snippet_syntax
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snippet {
+    pub text: String,
+    /// Tabstops in jump order, final tabstop (`$0`) last.
+    pub tabstops: Vec<Tabstop>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Tabstop {
+    pub index: u64,
+    /// Byte ranges in snippet text, one per occurrence of this tabstop.
+    pub ranges: Vec<(usize, usize)>,
+    pub choices: Vec<String>,
+}
+
+/// Position of a tabstop once snippet is inserted in buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabstopPosition {
+    /// Line relative to the line where snippet is inserted.
+    pub line: u64,
+    /// Byte column, relative to insert position on the first line.
+    pub col: u64,
+    /// Byte length of placeholder text.
+    pub len: u64,
+}
+
+impl Snippet {
+    /// Parse snippet, with `resolve` giving values of variables such as `TM_FILENAME`.
+    ///
+    /// Invalid syntax is kept as literal text.
+    pub fn parse<F>(snippet: &str, resolve: F) -> Snippet
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut parser = Parser {
+            chars: snippet.chars().collect(),
+            pos: 0,
+            text: String::new(),
+            tabstops: BTreeMap::new(),
+            resolve,
+        };
+        parser.parse_any(None);
+
+        let text = parser.text;
+        let mut tabstops = parser.tabstops;
+        let final_tabstop = tabstops.remove(&0).unwrap_or_else(|| Tabstop {
+            index: 0,
+            ranges: vec![(text.len(), text.len())],
+            choices: vec![],
+        });
+        let mut tabstops: Vec<_> = tabstops.into_values().collect();
+        tabstops.push(final_tabstop);
+
+        Snippet { text, tabstops }
+    }
+
+    /// Lines to insert, with `indent` prepended to all but the first, and positions of each
+    /// tabstop.
+    pub fn layout(&self, indent: &str) -> (Vec<String>, Vec<Vec<TabstopPosition>>) {
+        let lines = self
+            .text
+            .split('\n')
+            .enumerate()
+            .map(|(i, line)| {
+                if i == 0 {
+                    line.to_owned()
+                } else {
+                    format!("{}{}", indent, line)
+                }
+            }).collect();
+
+        let to_position = |&(start, end): &(usize, usize)| {
+            let before = &self.text[..start];
+            let line = before.matches('\n').count();
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+            let line_end = self.text[start..]
+                .find('\n')
+                .map_or(self.text.len(), |i| start + i);
+            let indent_len = if line == 0 { 0 } else { indent.len() };
+            TabstopPosition {
+                line: line as u64,
+                col: (start - line_start + indent_len) as u64,
+                len: (std::cmp::min(end, line_end) - start) as u64,
+            }
+        };
+        let positions = self
+            .tabstops
+            .iter()
+            .map(|tabstop| tabstop.ranges.iter().map(to_position).collect())
+            .collect();
+
+        (lines, positions)
+    }
+}
+
+/// Value of variable `name` of a snippet inserted at `line` (0-based) of `filename`.
+pub fn get_snippet_variable(
+    name: &str,
+    filename: &str,
+    line: u64,
+    line_text: &str,
+) -> Option<String> {
+    let path = Path::new(filename);
+    let to_string = |s: &std::ffi::OsStr| s.to_string_lossy().into_owned();
+    match name {
+        "TM_SELECTED_TEXT" => Some(String::new()),
+        "TM_CURRENT_LINE" => Some(line_text.to_owned()),
+        "TM_LINE_INDEX" => Some(line.to_string()),
+        "TM_LINE_NUMBER" => Some((line + 1).to_string()),
+        "TM_FILENAME" => path.file_name().map(to_string),
+        "TM_FILENAME_BASE" => path.file_stem().map(to_string),
+        "TM_DIRECTORY" => path.parent().map(|p| to_string(p.as_os_str())),
+        "TM_FILEPATH" => Some(filename.to_owned()),
+        _ => None,
+    }
+}
+
+struct Parser<F> {
+    chars: Vec<char>,
+    pos: usize,
+    text: String,
+    tabstops: BTreeMap<u64, Tabstop>,
+    resolve: F,
+}
+
+impl<F> Parser<F>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Parse text and nested snippet elements, until unescaped `until` or end of input.
+    fn parse_any(&mut self, until: Option<char>) {
+        while let Some(c) = self.peek() {
+            if Some(c) == until {
+                return;
+            }
+            match c {
+                '\\' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(c) if c == '$' || c == '}' || c == '\\' => {
+                            self.text.push(c);
+                            self.pos += 1;
+                        }
+                        _ => self.text.push('\\'),
+                    }
+                }
+                '$' => {
+                    let (pos, text_len, tabstops) =
+                        (self.pos, self.text.len(), self.tabstops.clone());
+                    if !self.parse_dollar() {
+                        // Not valid syntax, take it literally.
+                        self.pos = pos + 1;
+                        self.text.truncate(text_len);
+                        self.tabstops = tabstops;
+                        self.text.push('$');
+                    }
+                }
+                c => {
+                    self.text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn parse_int(&mut self) -> Option<u64> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    }
+
+    fn parse_var_name(&mut self) -> Option<String> {
+        match self.peek() {
+            Some(c) if c == '_' || c.is_ascii_alphabetic() => {}
+            _ => return None,
+        }
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c == '_' || c.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+        Some(self.chars[start..self.pos].iter().collect())
+    }
+
+    /// Text until unescaped `until`, which is consumed. Only `until`, `$`, `}` and `\` can be
+    /// escaped.
+    fn parse_text_until(&mut self, until: &[char]) -> Option<(String, char)> {
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            if until.contains(&c) {
+                return Some((text, c));
+            }
+            if c == '\\' {
+                match self.peek() {
+                    Some(next) if until.contains(&next) || "$}\\".contains(next) => {
+                        text.push(next);
+                        self.pos += 1;
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+            text.push(c);
+        }
+        None
+    }
+
+    fn add_range(&mut self, index: u64, start: usize) {
+        let end = self.text.len();
+        self.tabstops
+            .entry(index)
+            .or_insert_with(|| Tabstop {
+                index,
+                ..Tabstop::default()
+            }).ranges
+            .push((start, end));
+    }
+
+    /// Parse tabstop, placeholder, choice or variable, returning false if syntax is invalid.
+    fn parse_dollar(&mut self) -> bool {
+        self.pos += 1;
+        let braced = self.eat('{');
+
+        if let Some(index) = self.parse_int() {
+            let start = self.text.len();
+            if !braced || self.eat('}') {
+                // Mirror text of placeholder defined earlier.
+                let mirrored = self.tabstops.get(&index).and_then(|t| t.ranges.first().cloned());
+                if let Some((s, e)) = mirrored {
+                    let text = self.text[s..e].to_owned();
+                    self.text.push_str(&text);
+                }
+            } else if self.eat(':') {
+                self.parse_any(Some('}'));
+                if !self.eat('}') {
+                    return false;
+                }
+            } else if self.eat('|') {
+                let mut choices = vec![];
+                loop {
+                    match self.parse_text_until(&[',', '|']) {
+                        Some((choice, ',')) => choices.push(choice),
+                        Some((choice, _)) => {
+                            choices.push(choice);
+                            break;
+                        }
+                        None => return false,
+                    }
+                }
+                if !self.eat('}') {
+                    return false;
+                }
+                self.text.push_str(&choices[0]);
+                self.add_range(index, start);
+                if let Some(tabstop) = self.tabstops.get_mut(&index) {
+                    tabstop.choices = choices;
+                }
+                return true;
+            } else {
+                return false;
+            }
+            self.add_range(index, start);
+            return true;
+        }
+
+        let name = match self.parse_var_name() {
+            Some(name) => name,
+            None => return false,
+        };
+        let value = (self.resolve)(&name);
+        if !braced || self.eat('}') {
+            self.text.push_str(&value.unwrap_or(name));
+            return true;
+        }
+
+        if self.eat(':') {
+            let start = self.text.len();
+            self.parse_any(Some('}'));
+            if !self.eat('}') {
+                return false;
+            }
+            if let Some(value) = value {
+                // Drop default value, along with any tabstop in it.
+                self.text.truncate(start);
+                self.text.push_str(&value);
+                for tabstop in self.tabstops.values_mut() {
+                    tabstop.ranges.retain(|&(s, _)| s < start);
+                }
+                self.tabstops.retain(|_, t| !t.ranges.is_empty());
+            }
+            return true;
+        }
+
+        if self.eat('/') {
+            let (regex, format, options) = match (
+                self.parse_text_until(&['/']),
+                self.parse_text_until(&['/']),
+                self.parse_text_until(&['}']),
+            ) {
+                (Some((regex, _)), Some((format, _)), Some((options, _))) => {
+                    (regex, format, options)
+                }
+                _ => return false,
+            };
+            let value = value.unwrap_or_default();
+            let value = match regex::Regex::new(&regex) {
+                Ok(ref regex) if options.contains('g') => {
+                    regex.replace_all(&value, format.as_str()).into_owned()
+                }
+                Ok(ref regex) => regex.replace(&value, format.as_str()).into_owned(),
+                Err(_) => value,
+            };
+            self.text.push_str(&value);
+            return true;
+        }
+
+        false
+    }
+}
+
+#[test]
+fn test_snippet_parse() {
+    let parse = |s| {
+        Snippet::parse(s, |name| match name {
+            "TM_FILENAME" => Some("main.rs".to_owned()),
+            _ => None,
+        })
+    };
+    let tabstop = |index, ranges: &[(usize, usize)]| Tabstop {
+        index,
+        ranges: ranges.to_vec(),
+        choices: vec![],
+    };
+
+    assert_eq!(
+        parse("foo(${1:a}, ${2:b})$0"),
+        Snippet {
+            text: "foo(a, b)".into(),
+            tabstops: vec![tabstop(1, &[(4, 5)]), tabstop(2, &[(7, 8)]), tabstop(0, &[(9, 9)])],
+        }
+    );
+
+    // Nested placeholders, mirrors and no final tabstop.
+    assert_eq!(
+        parse("${1:x${2:y}} = $1;$3"),
+        Snippet {
+            text: "xy = xy;".into(),
+            tabstops: vec![
+                tabstop(1, &[(0, 2), (5, 7)]),
+                tabstop(2, &[(1, 2)]),
+                tabstop(3, &[(8, 8)]),
+                tabstop(0, &[(8, 8)]),
+            ],
+        }
+    );
+
+    // Choices.
+    let snippet = parse("${1|one,t\\,wo|}");
+    assert_eq!(snippet.text, "one");
+    assert_eq!(snippet.tabstops[0].choices, ["one", "t,wo"]);
+
+    // Variables, known and unknown, with default and transform.
+    assert_eq!(parse("$TM_FILENAME ${UNKNOWN} ${UNKNOWN:${1:d}}").text, "main.rs UNKNOWN d");
+    assert_eq!(parse("${TM_FILENAME:${1:d}}").tabstops, vec![tabstop(0, &[(7, 7)])]);
+    assert_eq!(parse("${TM_FILENAME/(.*)\\.rs/$1/}").text, "main");
+
+    // Escapes and invalid syntax.
+    assert_eq!(parse("\\$1 \\} \\a $ ${1 ${x").text, "$1 } \\a $ ${1 ${x");
+}
+
+#[test]
+fn test_snippet_layout() {
+    let snippet = Snippet::parse("fn ${1:name}() {\n\t${2:body}\n}", |_| None);
+    let (lines, positions) = snippet.layout("    ");
+    assert_eq!(lines, ["fn name() {", "    \tbody", "    }"]);
+    let position = |line, col, len| TabstopPosition { line, col, len };
+    assert_eq!(
+        positions,
+        [
+            vec![position(0, 3, 4)],
+            vec![position(1, 5, 4)],
+            vec![position(2, 5, 0)],
+        ]
+    );
+}
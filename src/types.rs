@@ -22,12 +22,22 @@ pub const REQUEST__IsAlive: &str = "languageClient/isAlive";
 pub const REQUEST__StartServer: &str = "languageClient/startServer";
 pub const REQUEST__RegisterServerCommands: &str = "languageClient/registerServerCommands";
 pub const REQUEST__OmniComplete: &str = "languageClient/omniComplete";
+pub const REQUEST__AutoComplete: &str = "languageClient/autoComplete";
 pub const REQUEST__SetLoggingLevel: &str = "languageClient/setLoggingLevel";
 pub const REQUEST__SetDiagnosticsList: &str = "languageClient/setDiagnosticsList";
+pub const REQUEST__ToggleDiagnosticsFilter: &str = "languageClient/toggleDiagnosticsFilter";
+pub const REQUEST__DiagnosticNext: &str = "languageClient/diagnosticNext";
+pub const REQUEST__DiagnosticPrev: &str = "languageClient/diagnosticPrev";
+pub const REQUEST__DiagnosticsSummary: &str = "languageClient/diagnosticsSummary";
 pub const REQUEST__RegisterHandlers: &str = "languageClient/registerHandlers";
 pub const REQUEST__NCMRefresh: &str = "LanguageClient_NCMRefresh";
 pub const REQUEST__NCM2OnComplete: &str = "LanguageClient_NCM2OnComplete";
+pub const REQUEST__CycleSignature: &str = "languageClient/cycleSignature";
 pub const REQUEST__ExplainErrorAtPoint: &str = "languageClient/explainErrorAtPoint";
+pub const REQUEST__QuickFix: &str = "languageClient/quickFix";
+pub const REQUEST__CodeActionsOnSave: &str = "languageClient/codeActionsOnSave";
+pub const REQUEST__FormatOnSave: &str = "languageClient/formatOnSave";
+pub const REQUEST__DiagnosticRelatedLocations: &str = "languageClient/diagnosticRelatedLocations";
 pub const REQUEST__FindLocations: &str = "languageClient/findLocations";
 pub const REQUEST__DebugInfo: &str = "languageClient/debugInfo";
 pub const NOTIFICATION__HandleBufNewFile: &str = "languageClient/handleBufNewFile";
@@ -37,6 +47,8 @@ pub const NOTIFICATION__HandleBufWritePost: &str = "languageClient/handleBufWrit
 pub const NOTIFICATION__HandleBufDelete: &str = "languageClient/handleBufDelete";
 pub const NOTIFICATION__HandleCursorMoved: &str = "languageClient/handleCursorMoved";
 pub const NOTIFICATION__HandleCompleteDone: &str = "languageClient/handleCompleteDone";
+pub const NOTIFICATION__HandleCompleteChanged: &str = "languageClient/handleCompleteChanged";
+pub const NOTIFICATION__HandleInsertModeChanged: &str = "languageClient/handleInsertModeChanged";
 pub const NOTIFICATION__FZFSinkLocation: &str = "LanguageClient_FZFSinkLocation";
 pub const NOTIFICATION__FZFSinkCommand: &str = "LanguageClient_FZFSinkCommand";
 pub const NOTIFICATION__ServerExited: &str = "$languageClient/serverExited";
@@ -54,6 +66,12 @@ pub const REQUEST__ClassFileContents: &str = "java/classFileContents";
 
 pub const CommandsClient: &[&str] = &["java.apply.workspaceEdit"];
 
+// Title of quickfix and location lists filled with diagnostics.
+pub const DiagnosticsListTitle: &str = "[LC]: diagnostics";
+
+// Responses to calls abandoned longer ago than this are not expected anymore.
+pub const AbandonedCallLifetime: Duration = Duration::from_secs(60);
+
 // Vim variable names
 pub const VIM__ServerStatus: &str = "g:LanguageClient_serverStatus";
 pub const VIM__ServerStatusMessage: &str = "g:LanguageClient_serverStatusMessage";
@@ -99,16 +117,23 @@ pub struct State {
     pub rx: Receiver<Message>,
     pub pending_calls: VecDeque<Call>,
     pub pending_outputs: HashMap<Id, rpc::Output>,
+    // Calls that stopped waiting for their response, e.g., after a deadline.
+    #[serde(skip_serializing)]
+    pub abandoned_calls: HashMap<Id, Instant>,
 
     pub child_ids: HashMap<String, u32>,
     #[serde(skip_serializing)]
     pub writers: HashMap<String, Box<dyn SyncWrite>>,
     pub capabilities: HashMap<String, Value>,
+    // languageId => negotiated position encoding.
+    pub position_encodings: HashMap<String, PositionEncoding>,
     pub registrations: Vec<Registration>,
     pub roots: HashMap<String, String>,
     pub text_documents: HashMap<String, TextDocumentItem>,
     pub text_documents_metadata: HashMap<String, TextDocumentItemMetadata>,
-    // filename => diagnostics.
+    // filename => diagnostics as published by server.
+    pub diagnostics_published: HashMap<String, Vec<Diagnostic>>,
+    // filename => diagnostics after diagnosticsFilter applied.
     pub diagnostics: HashMap<String, Vec<Diagnostic>>,
     #[serde(skip_serializing)]
     pub line_diagnostics: HashMap<(String, u64), String>,
@@ -117,9 +142,16 @@ pub struct State {
     pub highlight_source: Option<u64>,
     pub highlights: HashMap<String, Vec<Highlight>>,
     pub highlights_placed: HashMap<String, Vec<Highlight>>,
+    pub virtual_text_namespace: Option<u64>,
+    pub virtual_texts: HashMap<String, Vec<VirtualText>>,
+    pub virtual_texts_placed: HashMap<String, Vec<VirtualText>>,
     // TODO: make file specific.
     pub highlight_match_ids: Vec<u32>,
     pub document_highlight_source: Option<HighlightSource>,
+    // Match ids of document highlights in vim.
+    pub document_highlight_match_ids: Vec<u32>,
+    // Window document highlight matches were added to, as matches are per window.
+    pub document_highlight_match_winid: u64,
     pub user_handlers: HashMap<String, String>,
     #[serde(skip_serializing)]
     pub watchers: HashMap<String, notify::RecommendedWatcher>,
@@ -129,7 +161,12 @@ pub struct State {
     pub is_nvim: bool,
     pub last_cursor_line: u64,
     pub last_line_diagnostic: String,
-    pub stashed_codeAction_commands: Vec<Command>,
+    pub stashed_codeActions: Vec<CodeActionOrCommand>,
+    // Resolved completion items, keyed by serialized original item.
+    pub completion_resolved: HashMap<String, CompletionItem>,
+    pub completion_sessions: HashMap<String, CompletionSession>,
+    // Signature help being shown, by filename.
+    pub signature_help: HashMap<String, SignatureHelpResult>,
 
     // User settings.
     pub serverCommands: HashMap<String, Vec<String>>,
@@ -140,6 +177,11 @@ pub struct State {
     pub diagnosticsList: DiagnosticsList,
     pub diagnosticsDisplay: HashMap<u64, DiagnosticsDisplay>,
     pub diagnosticsSignsMax: Option<u64>,
+    pub diagnosticsFilter: DiagnosticsFilter,
+    pub useVirtualText: bool,
+    pub useFloatingHover: bool,
+    pub autoSignatureHelp: bool,
+    pub virtualTextPrefix: String,
     pub documentHighlightDisplay: HashMap<u64, DocumentHighlightDisplay>,
     pub windowLogMessageLevel: MessageType,
     pub settingsPath: String,
@@ -149,6 +191,14 @@ pub struct State {
     pub wait_output_timeout: Duration,
     pub hoverPreview: HoverPreviewOption,
     pub completionPreferTextEdit: bool,
+    pub completionDropDeprecated: bool,
+    pub completionFormat: CompletionFormat,
+    pub completionFormatByFiletype: HashMap<String, CompletionFormat>,
+    // languageId => code action kinds.
+    pub codeActionsOnSave: HashMap<String, Vec<String>>,
+    pub codeActionsOnSaveTimeout: Duration,
+    pub formatOnSave: Vec<String>,
+    pub formatOnSaveTimeout: Duration,
 
     pub loggingFile: Option<String>,
     pub loggingLevel: log::LevelFilter,
@@ -169,14 +219,17 @@ impl State {
             rx,
             pending_calls: VecDeque::new(),
             pending_outputs: HashMap::new(),
+            abandoned_calls: HashMap::new(),
 
             child_ids: HashMap::new(),
             writers: HashMap::new(),
             capabilities: HashMap::new(),
+            position_encodings: HashMap::new(),
             registrations: vec![],
             roots: HashMap::new(),
             text_documents: HashMap::new(),
             text_documents_metadata: HashMap::new(),
+            diagnostics_published: HashMap::new(),
             diagnostics: HashMap::new(),
             line_diagnostics: HashMap::new(),
             signs: HashMap::new(),
@@ -184,8 +237,13 @@ impl State {
             highlight_source: None,
             highlights: HashMap::new(),
             highlights_placed: HashMap::new(),
+            virtual_text_namespace: None,
+            virtual_texts: HashMap::new(),
+            virtual_texts_placed: HashMap::new(),
             highlight_match_ids: Vec::new(),
             document_highlight_source: None,
+            document_highlight_match_ids: Vec::new(),
+            document_highlight_match_winid: 0,
             user_handlers: HashMap::new(),
             watchers: HashMap::new(),
             watcher_rxs: HashMap::new(),
@@ -193,7 +251,10 @@ impl State {
             is_nvim: false,
             last_cursor_line: 0,
             last_line_diagnostic: " ".into(),
-            stashed_codeAction_commands: vec![],
+            stashed_codeActions: vec![],
+            completion_resolved: HashMap::new(),
+            completion_sessions: HashMap::new(),
+            signature_help: HashMap::new(),
 
             serverCommands: HashMap::new(),
             autoStart: true,
@@ -203,6 +264,11 @@ impl State {
             diagnosticsList: DiagnosticsList::Quickfix,
             diagnosticsDisplay: DiagnosticsDisplay::default(),
             diagnosticsSignsMax: None,
+            diagnosticsFilter: DiagnosticsFilter::default(),
+            useVirtualText: false,
+            useFloatingHover: false,
+            autoSignatureHelp: false,
+            virtualTextPrefix: "> ".into(),
             documentHighlightDisplay: DocumentHighlightDisplay::default(),
             windowLogMessageLevel: MessageType::Warning,
             settingsPath: format!(".vim{}settings.json", std::path::MAIN_SEPARATOR),
@@ -212,6 +278,13 @@ impl State {
             wait_output_timeout: Duration::from_secs(10),
             hoverPreview: HoverPreviewOption::default(),
             completionPreferTextEdit: false,
+            completionDropDeprecated: false,
+            completionFormat: CompletionFormat::default(),
+            completionFormatByFiletype: HashMap::new(),
+            codeActionsOnSave: HashMap::new(),
+            codeActionsOnSaveTimeout: Duration::from_secs(1),
+            formatOnSave: vec![],
+            formatOnSaveTimeout: Duration::from_secs(1),
             loggingFile: None,
             loggingLevel: log::LevelFilter::Warn,
             serverStderr: None,
@@ -273,10 +346,12 @@ impl FromStr for HoverPreviewOption {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DiagnosticsList {
     Quickfix,
     Location,
+    // Location list of each window filled with diagnostics of its own buffer.
+    Buffer,
     Disabled,
 }
 
@@ -293,12 +368,80 @@ impl FromStr for DiagnosticsList {
         match s.to_ascii_uppercase().as_str() {
             "QUICKFIX" => Ok(DiagnosticsList::Quickfix),
             "LOCATION" => Ok(DiagnosticsList::Location),
+            "BUFFER" => Ok(DiagnosticsList::Buffer),
             "DISABLED" => Ok(DiagnosticsList::Disabled),
             _ => bail!("Invalid option for LanguageClient_diagnosticsList: {}", s),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DiagnosticsFilterKind {
+    Severity,
+    Source,
+    Code,
+    Path,
+}
+
+impl FromStr for DiagnosticsFilterKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Fallible<Self> {
+        match s.to_ascii_uppercase().as_str() {
+            "SEVERITY" => Ok(DiagnosticsFilterKind::Severity),
+            "SOURCE" => Ok(DiagnosticsFilterKind::Source),
+            "CODE" => Ok(DiagnosticsFilterKind::Code),
+            "PATH" => Ok(DiagnosticsFilterKind::Path),
+            _ => bail!("Invalid diagnostics filter: {}", s),
+        }
+    }
+}
+
+/// Diagnostics matching any enabled filter are dropped before being displayed.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiagnosticsFilter {
+    pub minSeverity: Option<DiagnosticSeverity>,
+    pub ignoreSources: Vec<String>,
+    pub ignoreCodes: Vec<String>,
+    // Glob patterns matched against full file path.
+    pub ignorePaths: Vec<String>,
+    // Filters switched off at runtime.
+    pub disabled: HashSet<DiagnosticsFilterKind>,
+}
+
+impl DiagnosticsFilter {
+    pub fn is_enabled(&self, kind: DiagnosticsFilterKind) -> bool {
+        !self.disabled.contains(&kind)
+    }
+}
+
+/// Unit in which `Position.character` is counted.
+///
+/// LSP defaults to UTF-16 code units, while vim columns are byte offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PositionEncoding {
+    #[serde(rename = "utf-8")]
+    UTF8,
+    #[default]
+    #[serde(rename = "utf-16")]
+    UTF16,
+    #[serde(rename = "utf-32")]
+    UTF32,
+}
+
+impl FromStr for PositionEncoding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Fallible<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(PositionEncoding::UTF8),
+            "utf-16" | "utf16" => Ok(PositionEncoding::UTF16),
+            "utf-32" | "utf32" => Ok(PositionEncoding::UTF32),
+            _ => bail!("Invalid position encoding: {}", s),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiagnosticsDisplay {
     pub name: String,
@@ -452,10 +595,117 @@ pub struct Highlight {
 impl PartialEq for Highlight {
     fn eq(&self, other: &Self) -> bool {
         // Quick check whether highlight should be updated.
-        self.text == other.text && self.group == other.group
+        self.line == other.line
+            && self.character_start == other.character_start
+            && self.character_end == other.character_end
+            && self.text == other.text
+            && self.group == other.group
     }
 }
 
+/// Last completion result of a buffer, reused while user keeps typing the same word.
+#[derive(Debug, Clone, Serialize)]
+pub struct CompletionSession {
+    pub line: u64,
+    /// Line text before the word being completed.
+    pub prefix: String,
+    /// Part of the word typed when completion was requested.
+    pub base: String,
+    /// Hash of document lines other than `line`, to notice edits elsewhere.
+    pub document_hash: u64,
+    #[serde(skip_serializing)]
+    pub items: Vec<CompletionItem>,
+    pub is_incomplete: bool,
+}
+
+impl CompletionSession {
+    /// Whether items can be filtered locally instead of asking server again.
+    pub fn is_reusable(&self, line: u64, prefix: &str, base: &str, document_hash: u64) -> bool {
+        !self.is_incomplete
+            && self.line == line
+            && self.prefix == prefix
+            && base.starts_with(&self.base)
+            && self.document_hash == document_hash
+    }
+}
+
+#[test]
+fn test_completion_session_is_reusable() {
+    let session = CompletionSession {
+        line: 1,
+        prefix: "    self.".to_owned(),
+        base: "fo".to_owned(),
+        document_hash: 42,
+        items: vec![],
+        is_incomplete: false,
+    };
+    assert!(session.is_reusable(1, "    self.", "fo", 42));
+    assert!(session.is_reusable(1, "    self.", "foo", 42));
+    // Word deleted past the original base.
+    assert!(!session.is_reusable(1, "    self.", "f", 42));
+    // Other line, or word boundary moved.
+    assert!(!session.is_reusable(2, "    self.", "foo", 42));
+    assert!(!session.is_reusable(1, "    self.foo.", "", 42));
+    // Document edited elsewhere.
+    assert!(!session.is_reusable(1, "    self.", "foo", 43));
+
+    let session = CompletionSession {
+        is_incomplete: true,
+        ..session
+    };
+    assert!(!session.is_reusable(1, "    self.", "foo", 42));
+}
+
+// Signature help types accepting parameter label offsets and per signature active parameter, which
+// are not yet in languageserver-types.
+
+/// Parameter label, either a substring of signature label or its start and end offsets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ParameterLabel {
+    Simple(String),
+    LabelOffsets([u64; 2]),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureParameter {
+    pub label: ParameterLabel,
+    pub documentation: Option<Documentation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Signature {
+    pub label: String,
+    pub documentation: Option<Documentation>,
+    pub parameters: Option<Vec<SignatureParameter>>,
+    pub active_parameter: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureHelpResult {
+    pub signatures: Vec<Signature>,
+    pub active_signature: Option<u64>,
+    pub active_parameter: Option<u64>,
+}
+
+/// Number of diagnostics by severity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticsSummary {
+    pub error: u64,
+    pub warning: u64,
+    pub information: u64,
+    pub hint: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VirtualText {
+    pub line: u64,
+    pub text: String,
+    pub hl_group: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct QuickfixEntry {
     pub filename: String,
@@ -467,6 +717,51 @@ pub struct QuickfixEntry {
     pub typ: Option<char>,
 }
 
+/// `CodeAction` with fields not yet in `languageserver-types`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeActionLiteral {
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diagnostics: Option<Vec<Diagnostic>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edit: Option<WorkspaceEdit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<Command>,
+    #[serde(default)]
+    pub is_preferred: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CodeActionOrCommand {
+    Command(Command),
+    CodeAction(CodeActionLiteral),
+}
+
+impl CodeActionOrCommand {
+    pub fn title(&self) -> &str {
+        match self {
+            CodeActionOrCommand::Command(cmd) => &cmd.title,
+            CodeActionOrCommand::CodeAction(action) => &action.title,
+        }
+    }
+
+    /// Short description of what this does, e.g., command name or action kind.
+    pub fn label(&self) -> &str {
+        match self {
+            CodeActionOrCommand::Command(cmd) => &cmd.command,
+            CodeActionOrCommand::CodeAction(action) => action
+                .kind
+                .as_deref()
+                .or_else(|| action.command.as_ref().map(|cmd| cmd.command.as_str()))
+                .unwrap_or("action"),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NCMInfo {
     pub name: String,
@@ -522,6 +817,15 @@ pub struct NCM2Context {
     pub base: String,
 }
 
+/// How completion items are displayed in popup menu.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CompletionFormat {
+    /// Label of completion item kinds, by kind name. Kinds not in here are shown by name.
+    pub kinds: HashMap<String, String>,
+    /// Maximum width of detail in menu, 0 for no limit.
+    pub detailWidth: usize,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VimCompleteItem {
     pub word: String,
@@ -552,8 +856,14 @@ impl VimCompleteItem {
     pub fn from_lsp(
         lspitem: &CompletionItem,
         complete_position: Option<u64>,
+        encoding: PositionEncoding,
+        format: &CompletionFormat,
     ) -> Fallible<VimCompleteItem> {
-        let abbr = lspitem.label.clone();
+        let abbr = if lspitem.deprecated == Some(true) {
+            format!("{} (deprecated)", lspitem.label)
+        } else {
+            lspitem.label.clone()
+        };
         let mut word = lspitem.insert_text.clone().unwrap_or_default();
         if word.is_empty() {
             match (lspitem.text_edit.clone(), complete_position) {
@@ -561,9 +871,14 @@ impl VimCompleteItem {
                     // TextEdit range start might be different from vim expected completion start.
                     // From spec, TextEdit can only span one line, i.e., the current line.
                     if text_edit.range.start.character != complete_position {
+                        let offset = character_to_byte(
+                            &text_edit.new_text,
+                            complete_position.saturating_sub(text_edit.range.start.character),
+                            encoding,
+                        );
                         word = text_edit
                             .new_text
-                            .get((complete_position as usize)..)
+                            .get(offset..)
                             .and_then(|line| line.split_whitespace().next())
                             .map_or_else(String::new, ToOwned::to_owned);
                     } else {
@@ -584,15 +899,26 @@ impl VimCompleteItem {
         if lspitem.insert_text_format == Some(InsertTextFormat::Snippet) {
             is_snippet = Some(true);
             snippet = Some(word.clone());
+            // Insert plain text, in case snippet is not expanded afterwards.
+            let text = Snippet::parse(&word, |_| None).text;
+            word = text.lines().next().unwrap_or_default().to_owned();
         } else {
             is_snippet = None;
             snippet = None;
         };
 
-        let mut info = String::new();
-        if let Some(ref doc) = lspitem.documentation {
-            info += &doc.to_string();
-        }
+        let info = match lspitem.documentation {
+            Some(lsp::Documentation::MarkupContent(MarkupContent {
+                kind: MarkupKind::Markdown,
+                ref value,
+            })) => markdown_to_text(value),
+            Some(ref doc) => doc.to_string(),
+            None => String::new(),
+        };
+        let kind = lspitem.kind.map_or_else(String::new, |kind| {
+            let name = format!("{:?}", kind);
+            format.kinds.get(&name).cloned().unwrap_or(name)
+        });
 
         let user_data = VimCompleteItemUserData {
             lspitem: Some(lspitem.clone()),
@@ -603,18 +929,27 @@ impl VimCompleteItem {
             abbr,
             icase: Some(1),
             dup: Some(1),
-            menu: lspitem
-                .detail
-                .clone()
-                .unwrap_or_default()
-                .replace("\n", " "),
+            menu: truncate_text(
+                &lspitem.detail.clone().unwrap_or_default().replace("\n", " "),
+                format.detailWidth,
+            ),
             info,
-            kind: lspitem.kind.map(|k| format!("{:?}", k)).unwrap_or_default(),
+            kind,
             snippet,
             is_snippet,
             user_data: Some(serde_json::to_string(&user_data)?),
         })
     }
+
+    /// The LSP completion item this vim complete item was created from, if any.
+    pub fn lspitem(&self) -> Fallible<Option<CompletionItem>> {
+        let user_data = match self.user_data {
+            Some(ref user_data) if !user_data.is_empty() => user_data,
+            _ => return Ok(None),
+        };
+        let user_data: VimCompleteItemUserData = serde_json::from_str(user_data)?;
+        Ok(user_data.lspitem)
+    }
 }
 
 pub trait ToRpcError {
@@ -724,20 +1059,48 @@ impl ToString for NumberOrString {
     }
 }
 
+/// Lines of displayed text to be highlighted with syntax of another filetype, e.g., code blocks
+/// in markdown.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HighlightRegion {
+    pub filetype: String,
+    // Zero-based, inclusive.
+    pub start: u64,
+    pub end: u64,
+}
+
 pub trait ToDisplay {
     fn to_display(&self) -> Vec<String>;
     fn vim_filetype(&self) -> Option<String> {
         None
     }
+    /// Lines to display together with regions to highlight in them, rendering content only once.
+    fn to_display_with_regions(&self) -> (Vec<String>, Vec<HighlightRegion>) {
+        (self.to_display(), vec![])
+    }
+}
+
+/// Join marked strings into one markdown document, language strings as fenced code blocks.
+fn marked_strings_to_markdown(marked_strings: &[MarkedString]) -> String {
+    marked_strings
+        .iter()
+        .map(|ms| match ms {
+            MarkedString::String(ref s) => s.clone(),
+            MarkedString::LanguageString(ref ls) => {
+                format!("```{}\n{}\n```", ls.language, ls.value)
+            }
+        }).collect::<Vec<_>>()
+        .join("\n")
 }
 
 impl ToDisplay for lsp::MarkedString {
     fn to_display(&self) -> Vec<String> {
-        let s = match self {
-            MarkedString::String(ref s) => s,
-            MarkedString::LanguageString(ref ls) => &ls.value,
-        };
-        s.lines().map(|i| i.to_string()).collect()
+        match self {
+            MarkedString::String(ref s) => markdown_to_display(s).0,
+            MarkedString::LanguageString(ref ls) => {
+                ls.value.lines().map(|i| i.to_string()).collect()
+            }
+        }
     }
 
     fn vim_filetype(&self) -> Option<String> {
@@ -746,11 +1109,21 @@ impl ToDisplay for lsp::MarkedString {
             MarkedString::LanguageString(ref ls) => Some(ls.language.clone()),
         }
     }
+
+    fn to_display_with_regions(&self) -> (Vec<String>, Vec<HighlightRegion>) {
+        match self {
+            MarkedString::String(ref s) => markdown_to_display(s),
+            MarkedString::LanguageString(_) => (self.to_display(), vec![]),
+        }
+    }
 }
 
 impl ToDisplay for MarkupContent {
     fn to_display(&self) -> Vec<String> {
-        self.value.lines().map(str::to_string).collect()
+        match self.kind {
+            MarkupKind::Markdown => markdown_to_display(&self.value).0,
+            MarkupKind::PlainText => self.value.lines().map(str::to_string).collect(),
+        }
     }
 
     fn vim_filetype(&self) -> Option<String> {
@@ -759,27 +1132,22 @@ impl ToDisplay for MarkupContent {
             MarkupKind::PlainText => Some("text".to_string()),
         }
     }
+
+    fn to_display_with_regions(&self) -> (Vec<String>, Vec<HighlightRegion>) {
+        match self.kind {
+            MarkupKind::Markdown => markdown_to_display(&self.value),
+            MarkupKind::PlainText => (self.to_display(), vec![]),
+        }
+    }
 }
 
 impl ToDisplay for Hover {
     fn to_display(&self) -> Vec<String> {
         match self.contents {
             HoverContents::Scalar(ref ms) => ms.to_display(),
-            HoverContents::Array(ref arr) => arr
-                .iter()
-                .flat_map(|ms| {
-                    if let MarkedString::LanguageString(ref ls) = ms {
-                        let mut buf = Vec::new();
-
-                        buf.push(format!("```{}", ls.language));
-                        buf.extend(ls.value.lines().map(|i| i.to_string()));
-                        buf.push("```".to_string());
-
-                        buf
-                    } else {
-                        ms.to_display()
-                    }
-                }).collect(),
+            HoverContents::Array(ref arr) => {
+                markdown_to_display(&marked_strings_to_markdown(arr)).0
+            }
             HoverContents::Markup(ref mc) => mc.to_display(),
         }
     }
@@ -791,6 +1159,38 @@ impl ToDisplay for Hover {
             HoverContents::Markup(ref mc) => mc.vim_filetype(),
         }
     }
+
+    fn to_display_with_regions(&self) -> (Vec<String>, Vec<HighlightRegion>) {
+        match self.contents {
+            HoverContents::Scalar(ref ms) => ms.to_display_with_regions(),
+            HoverContents::Array(ref arr) => markdown_to_display(&marked_strings_to_markdown(arr)),
+            HoverContents::Markup(ref mc) => mc.to_display_with_regions(),
+        }
+    }
+}
+
+impl ToDisplay for CompletionItem {
+    fn to_display(&self) -> Vec<String> {
+        let mut lines = self
+            .detail
+            .as_ref()
+            .map(|detail| detail.to_display())
+            .unwrap_or_default();
+        if let Some(ref doc) = self.documentation {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.extend(doc.to_string().to_display());
+        }
+        lines
+    }
+
+    fn vim_filetype(&self) -> Option<String> {
+        match self.documentation {
+            Some(lsp::Documentation::MarkupContent(ref mc)) => mc.vim_filetype(),
+            _ => None,
+        }
+    }
 }
 
 impl ToDisplay for str {
@@ -125,14 +125,136 @@ impl<P: AsRef<Path> + std::fmt::Debug> ToUrl for P {
     }
 }
 
-pub fn apply_TextEdits(lines: &[String], edits: &[TextEdit]) -> Fallible<Vec<String>> {
+/// Convert a byte offset within `line` to a character offset counted in `encoding` units.
+pub fn byte_to_character(line: &str, byte: usize, encoding: PositionEncoding) -> u64 {
+    let mut byte = std::cmp::min(byte, line.len());
+    while !line.is_char_boundary(byte) {
+        byte -= 1;
+    }
+    let prefix = &line[..byte];
+
+    (match encoding {
+        PositionEncoding::UTF8 => prefix.len(),
+        PositionEncoding::UTF16 => prefix.encode_utf16().count(),
+        PositionEncoding::UTF32 => prefix.chars().count(),
+    }) as u64
+}
+
+/// Convert a character offset counted in `encoding` units to a byte offset within `line`.
+///
+/// Offsets past the end of line are clamped to the line length, as specified by LSP.
+pub fn character_to_byte(line: &str, character: u64, encoding: PositionEncoding) -> usize {
+    let mut count = 0;
+    for (idx, c) in line.char_indices() {
+        if count >= character {
+            return idx;
+        }
+        count += match encoding {
+            PositionEncoding::UTF8 => c.len_utf8(),
+            PositionEncoding::UTF16 => c.len_utf16(),
+            PositionEncoding::UTF32 => 1,
+        } as u64;
+    }
+
+    line.len()
+}
+
+#[test]
+fn test_position_encoding_conversion() {
+    // 'é' is 2 bytes and 1 UTF-16 unit. '😀' is 4 bytes and 2 UTF-16 units.
+    let line = "aé😀b";
+    assert_eq!(byte_to_character(line, 7, PositionEncoding::UTF8), 7);
+    assert_eq!(byte_to_character(line, 7, PositionEncoding::UTF16), 4);
+    assert_eq!(byte_to_character(line, 7, PositionEncoding::UTF32), 3);
+    // Byte offset in the middle of a character.
+    assert_eq!(byte_to_character(line, 2, PositionEncoding::UTF16), 1);
+    assert_eq!(byte_to_character(line, 100, PositionEncoding::UTF16), 5);
+
+    assert_eq!(character_to_byte(line, 7, PositionEncoding::UTF8), 7);
+    assert_eq!(character_to_byte(line, 4, PositionEncoding::UTF16), 7);
+    assert_eq!(character_to_byte(line, 3, PositionEncoding::UTF32), 7);
+    assert_eq!(character_to_byte(line, 2, PositionEncoding::UTF16), 3);
+    assert_eq!(character_to_byte(line, 100, PositionEncoding::UTF16), 8);
+    assert_eq!(character_to_byte("", 3, PositionEncoding::UTF16), 0);
+}
+
+/// Byte range of parameter within signature label. A parameter given as text is searched in the
+/// parameter list, preferring a whole word match.
+pub fn get_parameter_range(
+    label: &str,
+    parameter: &ParameterLabel,
+    encoding: PositionEncoding,
+) -> Option<(usize, usize)> {
+    match parameter {
+        ParameterLabel::LabelOffsets([start, end]) => {
+            let start = character_to_byte(label, *start, encoding);
+            let end = character_to_byte(label, *end, encoding);
+            if start < end {
+                Some((start, end))
+            } else {
+                None
+            }
+        }
+        ParameterLabel::Simple(ref text) => {
+            if text.is_empty() {
+                return None;
+            }
+
+            let is_word_char =
+                |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+            let offset = label.find('(').map_or(0, |i| i + 1);
+            let mut matches = label[offset..]
+                .match_indices(text.as_str())
+                .map(|(i, _)| (offset + i, offset + i + text.len()));
+            let first = matches.next()?;
+            let whole_word = std::iter::once(first).chain(matches).find(|&(start, end)| {
+                !is_word_char(label[..start].chars().next_back())
+                    && !is_word_char(label[end..].chars().next())
+            });
+            Some(whole_word.unwrap_or(first))
+        }
+    }
+}
+
+#[test]
+fn test_get_parameter_range() {
+    let label = "max(a: int, max: int) -> int";
+    let range = |parameter| get_parameter_range(label, &parameter, PositionEncoding::UTF16);
+    assert_eq!(range(ParameterLabel::Simple("a: int".into())), Some((4, 10)));
+    assert_eq!(range(ParameterLabel::Simple("max".into())), Some((12, 15)));
+    assert_eq!(range(ParameterLabel::Simple("a".into())), Some((4, 5)));
+    assert_eq!(range(ParameterLabel::Simple("b".into())), None);
+    assert_eq!(range(ParameterLabel::LabelOffsets([12, 20])), Some((12, 20)));
+    assert_eq!(range(ParameterLabel::LabelOffsets([5, 5])), None);
+
+    // Offsets counted in UTF-16 code units.
+    let label = "f(é, x)";
+    assert_eq!(
+        get_parameter_range(label, &ParameterLabel::LabelOffsets([5, 6]), PositionEncoding::UTF16),
+        Some((6, 7))
+    );
+}
+
+pub fn apply_TextEdits(
+    lines: &[String],
+    edits: &[TextEdit],
+    encoding: PositionEncoding,
+) -> Fallible<Vec<String>> {
+    // Position characters are counted in `encoding` units, convert them to byte offsets.
+    let to_byte = |line: usize, character: u64| -> Fallible<usize> {
+        match lines.get(line) {
+            Some(text) => Ok(character_to_byte(text, character, encoding)),
+            None => character.to_usize(),
+        }
+    };
+
     // Edits are ordered from bottom to top, from right to left.
     let mut edits_by_index = vec![];
     for edit in edits {
         let start_line = edit.range.start.line.to_usize()?;
-        let start_character: usize = edit.range.start.character.to_usize()?;
+        let start_character = to_byte(start_line, edit.range.start.character)?;
         let end_line: usize = edit.range.end.line.to_usize()?;
-        let end_character: usize = edit.range.end.character.to_usize()?;
+        let end_character = to_byte(end_line, edit.range.end.character)?;
 
         let start = lines[..std::cmp::min(start_line, lines.len())]
             .iter()
@@ -190,7 +312,7 @@ fn test_apply_TextEdit() {
 "#.to_owned(),
     };
 
-    assert_eq!(apply_TextEdits(&lines, &[edit]).unwrap(), expect);
+    assert_eq!(apply_TextEdits(&lines, &[edit], PositionEncoding::UTF16).unwrap(), expect);
 }
 
 #[test]
@@ -213,7 +335,276 @@ fn test_apply_TextEdit_overlong_end() {
         new_text: r#"nb = 123"#.to_owned(),
     };
 
-    assert_eq!(apply_TextEdits(&lines, &[edit]).unwrap(), expect);
+    assert_eq!(apply_TextEdits(&lines, &[edit], PositionEncoding::UTF16).unwrap(), expect);
+}
+
+#[test]
+fn test_apply_TextEdit_utf16() {
+    let lines: Vec<String> = vec!["let s = \"😀\"; x".to_owned()];
+    let expect: Vec<String> = vec!["let s = \"😀\"; y".to_owned()];
+
+    // `x` is at byte 16 but UTF-16 offset 14.
+    let edit = TextEdit {
+        range: Range {
+            start: Position {
+                line: 0,
+                character: 14,
+            },
+            end: Position {
+                line: 0,
+                character: 15,
+            },
+        },
+        new_text: "y".to_owned(),
+    };
+
+    assert_eq!(
+        apply_TextEdits(&lines, &[edit], PositionEncoding::UTF16).unwrap(),
+        expect
+    );
+}
+
+/// Position `cursor` moves to once `edits` are applied, so that it stays next to the same text.
+///
+/// A cursor inside a replaced range moves to the end of the replacement.
+pub fn get_cursor_after_TextEdits(
+    cursor: Position,
+    edits: &[TextEdit],
+    encoding: PositionEncoding,
+) -> Position {
+    let pos = |p: &Position| (p.line, p.character);
+    let mut edits: Vec<_> = edits.iter().collect();
+    // Bottom to top, so positions of remaining edits stay valid.
+    edits.sort_by_key(|edit| pos(&edit.range.start));
+    edits.reverse();
+
+    let mut cursor = cursor;
+    for edit in edits {
+        let (start, end) = (edit.range.start, edit.range.end);
+        if pos(&start) >= pos(&cursor) {
+            continue;
+        }
+
+        let new_lines: Vec<_> = edit.new_text.split('\n').collect();
+        let last = new_lines.last().cloned().unwrap_or_default();
+        let mut new_end = Position {
+            line: start.line + new_lines.len() as u64 - 1,
+            character: byte_to_character(last, last.len(), encoding),
+        };
+        if new_lines.len() == 1 {
+            new_end.character += start.character;
+        }
+
+        cursor = if pos(&end) > pos(&cursor) {
+            new_end
+        } else if end.line == cursor.line {
+            Position {
+                line: new_end.line,
+                character: new_end.character + cursor.character - end.character,
+            }
+        } else {
+            Position {
+                line: cursor.line + new_end.line - end.line,
+                character: cursor.character,
+            }
+        };
+    }
+
+    cursor
+}
+
+#[test]
+fn test_get_cursor_after_TextEdits() {
+    let edit = |l1, c1, l2, c2, text: &str| TextEdit {
+        range: Range {
+            start: Position {
+                line: l1,
+                character: c1,
+            },
+            end: Position {
+                line: l2,
+                character: c2,
+            },
+        },
+        new_text: text.to_owned(),
+    };
+    let pos = |line, character| Position { line, character };
+    let cursor_after = |edits: &[TextEdit]| {
+        get_cursor_after_TextEdits(pos(2, 5), edits, PositionEncoding::UTF16)
+    };
+
+    // Re-indent current line.
+    assert_eq!(cursor_after(&[edit(2, 0, 2, 4, "  ")]), pos(2, 3));
+    // Edits after cursor.
+    assert_eq!(cursor_after(&[edit(2, 5, 2, 5, "x"), edit(3, 0, 3, 0, "y")]), pos(2, 5));
+    // Lines joined and split before cursor.
+    assert_eq!(
+        cursor_after(&[edit(0, 3, 1, 0, ""), edit(2, 1, 2, 2, "a\nbcd")]),
+        pos(2, 6)
+    );
+    // Cursor inside replaced range.
+    assert_eq!(cursor_after(&[edit(2, 3, 2, 8, "é😀")]), pos(2, 6));
+}
+
+/// Find the smallest range of lines that differs between two versions of a buffer.
+///
+/// Returns `(start, end_prev, end)`, meaning lines `start..end_prev` of `lines_prev` should be
+/// replaced with lines `start..end` of `lines`.
+pub fn get_changed_lines_range(lines_prev: &[String], lines: &[String]) -> (usize, usize, usize) {
+    let prefix = lines_prev
+        .iter()
+        .zip(lines.iter())
+        .take_while(|(l1, l2)| l1 == l2)
+        .count();
+    let suffix_max = std::cmp::min(lines_prev.len(), lines.len()) - prefix;
+    let suffix = lines_prev
+        .iter()
+        .rev()
+        .zip(lines.iter().rev())
+        .take(suffix_max)
+        .take_while(|(l1, l2)| l1 == l2)
+        .count();
+
+    (prefix, lines_prev.len() - suffix, lines.len() - suffix)
+}
+
+#[test]
+fn test_apply_TextEdit_changed_lines_range() {
+    let lines: Vec<String> = r#"fn main() {
+0;
+}"#.lines()
+    .map(|l| l.to_owned())
+    .collect();
+
+    let edit = TextEdit {
+        range: Range {
+            start: Position {
+                line: 1,
+                character: 0,
+            },
+            end: Position {
+                line: 1,
+                character: 0,
+            },
+        },
+        new_text: "    ".to_owned(),
+    };
+
+    let lines_next = apply_TextEdits(&lines, &[edit], PositionEncoding::UTF16).unwrap();
+    assert_eq!(get_changed_lines_range(&lines, &lines_next), (1, 2, 2));
+    assert_eq!(lines_next[1], "    0;");
+}
+
+#[test]
+fn test_apply_TextEdit_changed_lines_range_insert_delete() {
+    let lines: Vec<String> = vec!["a".to_owned(), "b".to_owned(), "c".to_owned()];
+
+    let insert = TextEdit {
+        range: Range {
+            start: Position {
+                line: 1,
+                character: 0,
+            },
+            end: Position {
+                line: 1,
+                character: 0,
+            },
+        },
+        new_text: "x\ny\n".to_owned(),
+    };
+    let lines_next = apply_TextEdits(&lines, &[insert], PositionEncoding::UTF16).unwrap();
+    assert_eq!(lines_next, vec!["a", "x", "y", "b", "c"]);
+    assert_eq!(get_changed_lines_range(&lines, &lines_next), (1, 1, 3));
+
+    let delete = TextEdit {
+        range: Range {
+            start: Position {
+                line: 0,
+                character: 1,
+            },
+            end: Position {
+                line: 2,
+                character: 0,
+            },
+        },
+        new_text: "\n".to_owned(),
+    };
+    let lines_next = apply_TextEdits(&lines, &[delete], PositionEncoding::UTF16).unwrap();
+    assert_eq!(lines_next, vec!["a", "c"]);
+    assert_eq!(get_changed_lines_range(&lines, &lines_next), (1, 2, 1));
+
+    // Repeated lines must not make the prefix and suffix overlap.
+    let lines_prev: Vec<String> = vec!["a".to_owned(), "a".to_owned()];
+    let lines_next: Vec<String> = vec!["a".to_owned(), "a".to_owned(), "a".to_owned()];
+    assert_eq!(get_changed_lines_range(&lines_prev, &lines_next), (2, 2, 3));
+    assert_eq!(get_changed_lines_range(&lines_prev, &lines_prev), (2, 2, 2));
+}
+
+/// Split a range, given as `(line, byte column)` pairs, into one highlight per line.
+pub fn get_line_highlights(
+    lines: &[String],
+    start: (u64, u64),
+    end: (u64, u64),
+    group: &str,
+) -> Vec<Highlight> {
+    let mut highlights = vec![];
+    for line in start.0..=end.0 {
+        let text = lines.get(line as usize).map(String::as_str).unwrap_or_default();
+        let character_start = if line == start.0 { start.1 } else { 0 };
+        let character_end = if line == end.0 {
+            end.1
+        } else {
+            text.len() as u64
+        };
+        // A range ending at the very beginning of a line does not cover that line.
+        if line != start.0 && line == end.0 && character_end == 0 {
+            continue;
+        }
+
+        highlights.push(Highlight {
+            line,
+            character_start,
+            character_end,
+            group: group.to_owned(),
+            text: text
+                .get((character_start as usize)..(character_end as usize))
+                .map(ToOwned::to_owned)
+                .unwrap_or_default(),
+        });
+    }
+
+    highlights
+}
+
+#[test]
+fn test_get_line_highlights() {
+    let lines: Vec<String> = r#"fn main() {
+    let x = 1;
+}"#.lines()
+    .map(|l| l.to_owned())
+    .collect();
+
+    let highlights = get_line_highlights(&lines, (1, 8), (1, 9), "Error");
+    assert_eq!(highlights.len(), 1);
+    assert_eq!(highlights[0].text, "x");
+
+    let highlights = get_line_highlights(&lines, (0, 10), (2, 1), "Error");
+    let spans: Vec<_> = highlights
+        .iter()
+        .map(|h| (h.line, h.character_start, h.character_end, h.text.as_str()))
+        .collect();
+    assert_eq!(
+        spans,
+        vec![
+            (0, 10, 11, "{"),
+            (1, 0, 14, "    let x = 1;"),
+            (2, 0, 1, "}"),
+        ]
+    );
+
+    let highlights = get_line_highlights(&lines, (0, 0), (1, 0), "Error");
+    assert_eq!(highlights.len(), 1);
+    assert_eq!(highlights[0].text, "fn main() {");
 }
 
 fn get_command_add_sign(sign: &Sign, filename: &str) -> String {
@@ -247,6 +638,429 @@ fn test_get_command_add_sign() {
     );
 }
 
+/// Drop diagnostics matching any enabled filter.
+pub fn filter_diagnostics(
+    filter: &DiagnosticsFilter,
+    filename: &str,
+    diagnostics: &[Diagnostic],
+) -> Vec<Diagnostic> {
+    if filter.is_enabled(DiagnosticsFilterKind::Path)
+        && filter.ignorePaths.iter().any(|p| {
+            glob::Pattern::new(p)
+                .map(|p| p.matches(filename))
+                .unwrap_or(false)
+        }) {
+        return vec![];
+    }
+
+    diagnostics
+        .iter()
+        .filter(|dn| {
+            if !filter.is_enabled(DiagnosticsFilterKind::Severity) {
+                return true;
+            }
+            match (filter.minSeverity, dn.severity) {
+                (Some(min), Some(severity)) => severity as u64 <= min as u64,
+                _ => true,
+            }
+        }).filter(|dn| {
+            if !filter.is_enabled(DiagnosticsFilterKind::Source) {
+                return true;
+            }
+            match dn.source {
+                Some(ref source) => !filter.ignoreSources.contains(source),
+                None => true,
+            }
+        }).filter(|dn| {
+            if !filter.is_enabled(DiagnosticsFilterKind::Code) {
+                return true;
+            }
+            match dn.code {
+                Some(ref code) => !filter.ignoreCodes.contains(&code.to_string()),
+                None => true,
+            }
+        }).cloned()
+        .collect()
+}
+
+#[test]
+fn test_filter_diagnostics() {
+    let dn = |severity, source: &str, code| Diagnostic {
+        severity: Some(severity),
+        source: Some(source.to_owned()),
+        code: Some(code),
+        ..Diagnostic::default()
+    };
+    let diagnostics = vec![
+        dn(DiagnosticSeverity::Error, "rustc", NumberOrString::String("E0308".into())),
+        dn(DiagnosticSeverity::Hint, "rustc", NumberOrString::String("E0599".into())),
+        dn(DiagnosticSeverity::Warning, "clippy", NumberOrString::Number(42)),
+    ];
+
+    let mut filter = DiagnosticsFilter::default();
+    assert_eq!(filter_diagnostics(&filter, "/src/a.rs", &diagnostics), diagnostics);
+
+    filter.minSeverity = Some(DiagnosticSeverity::Warning);
+    assert_eq!(
+        filter_diagnostics(&filter, "/src/a.rs", &diagnostics),
+        vec![diagnostics[0].clone(), diagnostics[2].clone()]
+    );
+
+    filter.ignoreSources = vec!["clippy".into()];
+    filter.ignoreCodes = vec!["E0308".into()];
+    assert_eq!(filter_diagnostics(&filter, "/src/a.rs", &diagnostics), vec![]);
+
+    filter.disabled.insert(DiagnosticsFilterKind::Severity);
+    filter.disabled.insert(DiagnosticsFilterKind::Code);
+    assert_eq!(
+        filter_diagnostics(&filter, "/src/a.rs", &diagnostics),
+        vec![diagnostics[0].clone(), diagnostics[1].clone()]
+    );
+
+    filter.ignoreCodes = vec!["42".into()];
+    filter.disabled.clear();
+    filter.disabled.insert(DiagnosticsFilterKind::Source);
+    assert_eq!(
+        filter_diagnostics(&filter, "/src/a.rs", &diagnostics),
+        vec![diagnostics[0].clone()]
+    );
+
+    filter.ignorePaths = vec!["**/generated/**".into()];
+    assert_eq!(
+        filter_diagnostics(&filter, "/src/generated/a.rs", &diagnostics),
+        vec![]
+    );
+    assert_eq!(
+        filter_diagnostics(&filter, "/src/a.rs", &diagnostics),
+        vec![diagnostics[0].clone()]
+    );
+}
+
+pub fn parse_diagnostic_severity(s: &str) -> Option<DiagnosticSeverity> {
+    match s.to_ascii_uppercase().as_str() {
+        "ERROR" => Some(DiagnosticSeverity::Error),
+        "WARNING" => Some(DiagnosticSeverity::Warning),
+        "INFORMATION" => Some(DiagnosticSeverity::Information),
+        "HINT" => Some(DiagnosticSeverity::Hint),
+        _ => None,
+    }
+}
+
+pub fn get_diagnostic_message(dn: &Diagnostic) -> String {
+    let mut msg = String::new();
+    if let Some(severity) = dn.severity {
+        msg += &format!("[{:?}]", severity);
+    }
+    if let Some(ref code) = dn.code {
+        let s = code.to_string();
+        if !s.is_empty() {
+            msg += &format!("[{}]", s);
+        }
+    }
+    msg += &dn.message;
+    msg
+}
+
+/// Text to explain a diagnostic, including its source, code and related information.
+///
+/// `related` are the related locations, with paths shown relative to `cwd`.
+pub fn get_diagnostic_explanation(dn: &Diagnostic, related: &[QuickfixEntry], cwd: &str) -> String {
+    let mut explanation = dn.message.clone();
+
+    let code = dn.code.as_ref().map(|c| c.to_string()).unwrap_or_default();
+    let origin = match (dn.source.as_ref(), code.is_empty()) {
+        (Some(source), false) => format!("{}({})", source, code),
+        (Some(source), true) => source.clone(),
+        (None, false) => code,
+        (None, true) => String::new(),
+    };
+    if !origin.is_empty() {
+        explanation += &format!("\n\n{}", origin);
+    }
+
+    if !related.is_empty() {
+        explanation += "\n\nRelated information:";
+        for entry in related {
+            let path = diff_paths(Path::new(&entry.filename), Path::new(cwd))
+                .unwrap_or_else(|| PathBuf::from(&entry.filename));
+            explanation += &format!(
+                "\n{}:{}:{}: {}",
+                path.to_string_lossy(),
+                entry.lnum,
+                entry.col.unwrap_or(1),
+                entry.text.as_deref().unwrap_or_default()
+            );
+        }
+    }
+
+    explanation
+}
+
+#[test]
+fn test_get_diagnostic_explanation() {
+    let mut dn = Diagnostic {
+        message: "cannot borrow `x` as mutable more than once at a time".into(),
+        ..Diagnostic::default()
+    };
+    assert_eq!(get_diagnostic_explanation(&dn, &[], "/project"), dn.message);
+
+    dn.source = Some("rustc".into());
+    dn.code = Some(NumberOrString::String("E0499".into()));
+    let related = vec![QuickfixEntry {
+        filename: "/project/src/main.rs".into(),
+        lnum: 3,
+        col: Some(14),
+        nr: None,
+        text: Some("first mutable borrow occurs here".into()),
+        typ: None,
+    }];
+    assert_eq!(
+        get_diagnostic_explanation(&dn, &related, "/project"),
+        "cannot borrow `x` as mutable more than once at a time
+
+rustc(E0499)
+
+Related information:
+src/main.rs:3:14: first mutable borrow occurs here"
+    );
+}
+
+pub fn get_diagnostics_summary<'a, I>(diagnostics: I) -> DiagnosticsSummary
+where
+    I: IntoIterator<Item = &'a Diagnostic>,
+{
+    let mut summary = DiagnosticsSummary::default();
+    for dn in diagnostics {
+        match dn.severity.unwrap_or(DiagnosticSeverity::Hint) {
+            DiagnosticSeverity::Error => summary.error += 1,
+            DiagnosticSeverity::Warning => summary.warning += 1,
+            DiagnosticSeverity::Information => summary.information += 1,
+            DiagnosticSeverity::Hint => summary.hint += 1,
+        }
+    }
+    summary
+}
+
+#[test]
+fn test_get_diagnostics_summary() {
+    let dn = |severity| Diagnostic {
+        severity,
+        ..Diagnostic::default()
+    };
+    let diagnostics = vec![
+        dn(Some(DiagnosticSeverity::Error)),
+        dn(Some(DiagnosticSeverity::Warning)),
+        dn(Some(DiagnosticSeverity::Error)),
+        dn(None),
+    ];
+
+    assert_eq!(
+        get_diagnostics_summary(&diagnostics),
+        DiagnosticsSummary {
+            error: 2,
+            warning: 1,
+            information: 0,
+            hint: 1,
+        }
+    );
+    assert_eq!(get_diagnostics_summary(&[]), DiagnosticsSummary::default());
+}
+
+/// Whether a diagnostic at `dn_range` applies to `range`. Empty `range` is a cursor position.
+pub fn is_diagnostic_in_range(dn_range: &Range, range: &Range) -> bool {
+    let pos = |p: &Position| (p.line, p.character);
+    if range.start == range.end {
+        pos(&dn_range.start) <= pos(&range.start) && pos(&range.start) < pos(&dn_range.end)
+    } else {
+        pos(&dn_range.start) < pos(&range.end) && pos(&range.start) < pos(&dn_range.end)
+    }
+}
+
+#[test]
+fn test_is_diagnostic_in_range() {
+    let range = |l1, c1, l2, c2| Range {
+        start: Position {
+            line: l1,
+            character: c1,
+        },
+        end: Position {
+            line: l2,
+            character: c2,
+        },
+    };
+    let dn_range = range(2, 4, 2, 8);
+
+    assert!(is_diagnostic_in_range(&dn_range, &range(2, 4, 2, 4)));
+    assert!(is_diagnostic_in_range(&dn_range, &range(2, 7, 2, 7)));
+    assert!(!is_diagnostic_in_range(&dn_range, &range(2, 8, 2, 8)));
+    assert!(is_diagnostic_in_range(&dn_range, &range(1, 0, 3, 0)));
+    assert!(is_diagnostic_in_range(&dn_range, &range(2, 0, 3, 0)));
+    assert!(!is_diagnostic_in_range(&dn_range, &range(0, 0, 2, 0)));
+    assert!(!is_diagnostic_in_range(&dn_range, &range(3, 0, 4, 0)));
+}
+
+/// Whether code action `kind` is `base` or a sub kind of it, e.g., "refactor.extract" of
+/// "refactor".
+pub fn is_code_action_kind(kind: &str, base: &str) -> bool {
+    kind == base || (kind.starts_with(base) && kind[base.len()..].starts_with('.'))
+}
+
+/// Quick fix to apply without asking: the preferred one, or the only one available.
+pub fn get_preferred_quickfix(actions: &[CodeActionOrCommand]) -> Option<&CodeActionOrCommand> {
+    let quickfixes: Vec<_> = actions
+        .iter()
+        .filter(|action| match action {
+            CodeActionOrCommand::CodeAction(action) => action
+                .kind
+                .as_ref()
+                .is_some_and(|kind| is_code_action_kind(kind, code_action_kind::QUICKFIX)),
+            CodeActionOrCommand::Command(_) => false,
+        }).collect();
+
+    let preferred = quickfixes.iter().find(|action| match action {
+        CodeActionOrCommand::CodeAction(action) => action.is_preferred,
+        CodeActionOrCommand::Command(_) => false,
+    });
+    match preferred {
+        Some(action) => Some(action),
+        None if quickfixes.len() == 1 => Some(quickfixes[0]),
+        None => None,
+    }
+}
+
+#[test]
+fn test_get_preferred_quickfix() {
+    assert!(is_code_action_kind("refactor.extract", "refactor"));
+    assert!(is_code_action_kind("quickfix", "quickfix"));
+    assert!(!is_code_action_kind("quickfixes", "quickfix"));
+    assert!(!is_code_action_kind("refactor", "refactor.extract"));
+
+    let action = |title: &str, kind: &str, is_preferred| {
+        CodeActionOrCommand::CodeAction(CodeActionLiteral {
+            title: title.into(),
+            kind: Some(kind.into()),
+            is_preferred,
+            ..CodeActionLiteral::default()
+        })
+    };
+    let command = CodeActionOrCommand::Command(Command {
+        title: "Run".into(),
+        command: "run".into(),
+        arguments: None,
+    });
+
+    let actions = vec![
+        command.clone(),
+        action("Extract", "refactor.extract", true),
+        action("Import", "quickfix", false),
+    ];
+    assert_eq!(get_preferred_quickfix(&actions), Some(&actions[2]));
+
+    let actions = vec![
+        action("Import a", "quickfix", false),
+        action("Import b", "quickfix.import", true),
+    ];
+    assert_eq!(get_preferred_quickfix(&actions), Some(&actions[1]));
+
+    let actions = vec![
+        action("Import a", "quickfix", false),
+        action("Import b", "quickfix", false),
+        command,
+    ];
+    assert_eq!(get_preferred_quickfix(&actions), None);
+}
+
+#[test]
+fn test_deserialize_code_action_or_command() {
+    let actions: Vec<CodeActionOrCommand> = serde_json::from_value(json!([
+        {"title": "Run", "command": "run"},
+        {
+            "title": "Fix",
+            "kind": "quickfix",
+            "isPreferred": true,
+            "command": {"title": "Fix", "command": "fix"},
+        },
+        {"title": "Edit", "edit": {"changes": {}}},
+    ])).unwrap();
+    assert_eq!(actions[0].label(), "run");
+    assert_eq!(actions[1].label(), "quickfix");
+    assert_eq!(actions[2].label(), "action");
+    match actions[1] {
+        CodeActionOrCommand::CodeAction(ref action) => assert!(action.is_preferred),
+        _ => panic!("Expecting CodeAction"),
+    }
+}
+
+/// Find the closest diagnostic after (or before) position, wrapping around the file.
+///
+/// Diagnostics less severe than `min_severity` are skipped.
+pub fn find_next_diagnostic<'a>(
+    diagnostics: &'a [Diagnostic],
+    position: &Position,
+    min_severity: Option<DiagnosticSeverity>,
+    forward: bool,
+) -> Option<&'a Diagnostic> {
+    let mut candidates: Vec<_> = diagnostics
+        .iter()
+        .filter(|dn| match min_severity {
+            Some(min) => dn.severity.unwrap_or(DiagnosticSeverity::Hint) as u64 <= min as u64,
+            None => true,
+        }).collect();
+    candidates.sort_by_key(|dn| (dn.range.start.line, dn.range.start.character));
+
+    let position = (position.line, position.character);
+    let start = |dn: &&Diagnostic| (dn.range.start.line, dn.range.start.character);
+    if forward {
+        candidates
+            .iter()
+            .find(|dn| start(dn) > position)
+            .or_else(|| candidates.first())
+            .cloned()
+    } else {
+        candidates
+            .iter()
+            .rev()
+            .find(|dn| start(dn) < position)
+            .or_else(|| candidates.last())
+            .cloned()
+    }
+}
+
+#[test]
+fn test_find_next_diagnostic() {
+    let dn = |line, character, severity| Diagnostic {
+        range: Range {
+            start: Position { line, character },
+            end: Position { line, character },
+        },
+        severity: Some(severity),
+        ..Diagnostic::default()
+    };
+    let diagnostics = vec![
+        dn(5, 0, DiagnosticSeverity::Warning),
+        dn(1, 4, DiagnosticSeverity::Error),
+        dn(3, 2, DiagnosticSeverity::Hint),
+    ];
+    let pos = |line, character| Position { line, character };
+
+    let next = |p, s| find_next_diagnostic(&diagnostics, &p, s, true).cloned();
+    let prev = |p, s| find_next_diagnostic(&diagnostics, &p, s, false).cloned();
+
+    assert_eq!(next(pos(1, 4), None), Some(diagnostics[2].clone()));
+    assert_eq!(next(pos(5, 0), None), Some(diagnostics[1].clone()));
+    assert_eq!(prev(pos(3, 2), None), Some(diagnostics[1].clone()));
+    assert_eq!(prev(pos(1, 0), None), Some(diagnostics[0].clone()));
+    assert_eq!(
+        next(pos(1, 4), Some(DiagnosticSeverity::Warning)),
+        Some(diagnostics[0].clone())
+    );
+    assert_eq!(
+        prev(pos(1, 4), Some(DiagnosticSeverity::Error)),
+        Some(diagnostics[1].clone())
+    );
+    assert_eq!(find_next_diagnostic(&[], &pos(0, 0), None, true), None);
+}
+
 fn get_command_delete_sign(sign: &Sign, filename: &str) -> String {
     format!("sign unplace {} file={}", sign.id, filename)
 }
@@ -472,6 +1286,571 @@ fn test_diff_value() {
     );
 }
 
+/// Score `text` as a fuzzy match of `pattern`, ignoring case. Higher is better.
+///
+/// Characters of `pattern` must appear in `text` in order. Matches that are consecutive, at word
+/// boundaries or of the same case score higher, and so do matches close to the start of `text`.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.chars().collect();
+    let mut score = 0;
+    let mut index = 0;
+    let mut last_match: Option<usize> = None;
+    for p in pattern.chars() {
+        while !text.get(index)?.to_lowercase().eq(p.to_lowercase()) {
+            index += 1;
+        }
+
+        score += if text[index] == p { 2 } else { 1 };
+        let is_boundary = index == 0 || {
+            let prev = text[index - 1];
+            !prev.is_alphanumeric() || (prev.is_lowercase() && text[index].is_uppercase())
+        };
+        match last_match {
+            Some(last) if last + 1 == index => score += 5,
+            _ if is_boundary => score += 3,
+            Some(last) => score -= std::cmp::min(index - last - 1, 3) as i64,
+            None => score -= std::cmp::min(index, 3) as i64,
+        }
+
+        last_match = Some(index);
+        index += 1;
+    }
+
+    Some(score)
+}
+
+/// Filter and rank completion items against `base`, the text typed since completion start.
+///
+/// Items are matched by `filter_text` (or label). Preselected items come first, deprecated ones
+/// last, then by score. Ties keep `sort_text` (or label) order, then the order from server.
+pub fn rank_completion_items(
+    items: Vec<CompletionItem>,
+    base: &str,
+    drop_deprecated: bool,
+) -> Vec<CompletionItem> {
+    fn text_or_label<'a>(text: &'a Option<String>, label: &'a str) -> &'a str {
+        match text {
+            Some(text) if !text.is_empty() => text,
+            _ => label,
+        }
+    }
+
+    let mut scored: Vec<_> = items
+        .into_iter()
+        .filter(|item| !drop_deprecated || item.deprecated != Some(true))
+        .filter_map(|item| {
+            let score = fuzzy_match(base, text_or_label(&item.filter_text, &item.label))?;
+            Some((score, item))
+        }).collect();
+
+    fn key((score, item): &(i64, CompletionItem)) -> (bool, bool, i64, &str) {
+        (
+            item.preselect != Some(true),
+            item.deprecated == Some(true),
+            -score,
+            text_or_label(&item.sort_text, &item.label),
+        )
+    }
+    scored.sort_by(|a, b| key(a).cmp(&key(b)));
+    scored.into_iter().map(|(_, item)| item).collect()
+}
+
+#[test]
+fn test_fuzzy_match() {
+    assert!(fuzzy_match("", "anything").is_some());
+    assert!(fuzzy_match("fb", "foo_bar").is_some());
+    assert!(fuzzy_match("FB", "fooBar").is_some());
+    assert!(fuzzy_match("bf", "foo_bar").is_none());
+    assert!(fuzzy_match("foo", "fo").is_none());
+
+    // Prefix beats word boundaries beats scattered characters.
+    assert!(fuzzy_match("set", "setValue") > fuzzy_match("set", "resetValue"));
+    assert!(fuzzy_match("gv", "getValue") > fuzzy_match("gv", "gravel"));
+    assert!(fuzzy_match("len", "len") > fuzzy_match("len", "Len"));
+}
+
+#[test]
+fn test_rank_completion_items() {
+    let item = |label: &str| CompletionItem {
+        label: label.into(),
+        ..CompletionItem::default()
+    };
+    let labels =
+        |items: Vec<CompletionItem>| items.into_iter().map(|i| i.label).collect::<Vec<_>>();
+
+    let items = vec![
+        CompletionItem {
+            sort_text: Some("2".into()),
+            ..item("push")
+        },
+        CompletionItem {
+            sort_text: Some("1".into()),
+            ..item("pop")
+        },
+        CompletionItem {
+            deprecated: Some(true),
+            ..item("pos")
+        },
+        CompletionItem {
+            filter_text: Some("position".into()),
+            ..item("pos()")
+        },
+        item("capacity"),
+        CompletionItem {
+            preselect: Some(true),
+            ..item("append")
+        },
+    ];
+
+    assert_eq!(
+        labels(rank_completion_items(items.clone(), "", false)),
+        ["append", "pop", "push", "capacity", "pos()", "pos"]
+    );
+    assert_eq!(
+        labels(rank_completion_items(items.clone(), "po", false)),
+        ["pop", "pos()", "pos"]
+    );
+    assert_eq!(
+        labels(rank_completion_items(items.clone(), "pst", true)),
+        ["pos()"]
+    );
+}
+
+#[test]
+fn test_rank_completion_items_many() {
+    let items: Vec<_> = (0..20_000)
+        .map(|i| CompletionItem {
+            label: format!("item{}_{}", i % 97, i),
+            sort_text: Some(format!("{:05}", 20_000 - i)),
+            ..CompletionItem::default()
+        }).collect();
+
+    let ranked = rank_completion_items(items.clone(), "", false);
+    assert_eq!(ranked.len(), 20_000);
+    assert_eq!(ranked[0].label, "item17_19999");
+    let ranked = rank_completion_items(items, "it1_2", false);
+    assert!(ranked[0].label.starts_with("item1_2"));
+    assert!(ranked.len() > 1_000);
+}
+
+// Run with `cargo test --release -- --ignored`.
+#[test]
+#[ignore]
+fn bench_rank_completion_items() {
+    let items: Vec<_> = (0..20_000)
+        .map(|i| CompletionItem {
+            label: format!("someCompletionItem{}_{}", i % 97, i),
+            sort_text: Some(format!("{:05}", i)),
+            ..CompletionItem::default()
+        }).collect();
+
+    let start = Instant::now();
+    for base in &["", "s", "sci", "someItem1", "sCI12_3"] {
+        rank_completion_items(items.clone(), base, false);
+    }
+    let elapsed = start.elapsed();
+    assert!(
+        elapsed < Duration::from_millis(500),
+        "Ranking took {:?}",
+        elapsed
+    );
+}
+
+/// Hash of all lines of `text` but `line`, to tell whether anything else in document changed.
+pub fn hash_other_lines(text: &str, line: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for (i, text) in text.lines().enumerate() {
+        if i as u64 != line {
+            (i, text).hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+#[test]
+fn test_hash_other_lines() {
+    let text = "fn main() {\n    foo.ba\n}\n";
+    let hash = hash_other_lines(text, 1);
+    assert_eq!(hash_other_lines("fn main() {\n    foo.bar\n}\n", 1), hash);
+    assert_ne!(hash_other_lines("fn main() {\n    foo.ba\n}\nfn foo() {}\n", 1), hash);
+    assert_ne!(hash_other_lines("fn  main() {\n    foo.ba\n}\n", 1), hash);
+    assert_ne!(hash_other_lines(text, 0), hash);
+}
+
+/// Truncate `text` to at most `width` characters, marking truncation with an ellipsis. A width
+/// of 0 means no limit.
+pub fn truncate_text(text: &str, width: usize) -> String {
+    if width == 0 || text.chars().count() <= width {
+        return text.to_owned();
+    }
+
+    let mut truncated: String = text.chars().take(width - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[test]
+fn test_truncate_text() {
+    assert_eq!(truncate_text("fn foo()", 0), "fn foo()");
+    assert_eq!(truncate_text("fn foo()", 8), "fn foo()");
+    assert_eq!(truncate_text("fn foo()", 5), "fn f…");
+    assert_eq!(truncate_text("éèêë", 3), "éè…");
+}
+
+// Characters escaped with backslash are moved to private use area while removing markup, so they
+// are not taken for markup.
+const MARKDOWN_ESCAPE_OFFSET: u32 = 0xE000;
+
+fn markdown_escape(line: &str) -> String {
+    let mut escaped = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(&next) if c == '\\' && next.is_ascii_punctuation() => {
+                chars.next();
+                escaped.extend(std::char::from_u32(MARKDOWN_ESCAPE_OFFSET + next as u32));
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn markdown_unescape(line: &str) -> String {
+    line.chars()
+        .map(|c| match (c as u32).checked_sub(MARKDOWN_ESCAPE_OFFSET) {
+            Some(c) if c < 128 => std::char::from_u32(c).unwrap_or_default(),
+            _ => c,
+        }).collect()
+}
+
+/// Removes inline markdown markup, e.g., headings, emphasis, code spans and links.
+struct MarkdownInline {
+    link: regex::Regex,
+    replacements: Vec<(regex::Regex, &'static str)>,
+}
+
+impl MarkdownInline {
+    fn new() -> Fallible<Self> {
+        let replacements = [
+            (r"^#{1,6}\s+", ""),
+            (r"\*\*([^*]+)\*\*", "$1"),
+            (r"\b__([^_]+)__\b", "$1"),
+            (r"\*([^*\s][^*]*)\*", "$1"),
+            (r"\b_([^_]+)_\b", "$1"),
+            (r"`([^`]*)`", "$1"),
+        ];
+        let mut compiled = vec![];
+        for &(regex, replacement) in &replacements {
+            compiled.push((regex::Regex::new(regex)?, replacement));
+        }
+
+        Ok(MarkdownInline {
+            link: regex::Regex::new(r"!?\[([^\]]*)\]\(([^)]*)\)")?,
+            replacements: compiled,
+        })
+    }
+
+    /// Strip markup of one line. Links are replaced by their text, followed by a footnote
+    /// reference to their target in `footnotes` if given.
+    fn strip(&self, line: &str, mut footnotes: Option<&mut Vec<String>>) -> String {
+        let line = markdown_escape(line);
+        let mut line = self
+            .link
+            .replace_all(&line, |caps: &regex::Captures| {
+                let text = &caps[1];
+                let target = markdown_unescape(caps[2].trim());
+                match footnotes {
+                    Some(ref mut footnotes) if !target.is_empty() => {
+                        let index = match footnotes.iter().position(|t| *t == target) {
+                            Some(index) => index,
+                            None => {
+                                footnotes.push(target);
+                                footnotes.len() - 1
+                            }
+                        };
+                        format!("{}[{}]", text, index + 1)
+                    }
+                    _ => text.to_owned(),
+                }
+            }).into_owned();
+        for (regex, replacement) in &self.replacements {
+            line = regex.replace_all(&line, *replacement).into_owned();
+        }
+        markdown_unescape(&line)
+    }
+}
+
+lazy_static! {
+    // Compiled once, as markdown is rendered for every completion item.
+    static ref MARKDOWN_INLINE: MarkdownInline =
+        MarkdownInline::new().expect("Failed to compile markdown regexes");
+}
+
+/// Render markdown as plain text: markup is removed, code blocks are kept verbatim.
+pub fn markdown_to_text(markdown: &str) -> String {
+    let inline = &*MARKDOWN_INLINE;
+
+    let mut in_code_block = false;
+    let mut lines = vec![];
+    for line in markdown.lines() {
+        if line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            lines.push(line.to_owned());
+            continue;
+        }
+
+        lines.push(inline.strip(line, None));
+    }
+
+    lines.join("\n")
+}
+
+#[test]
+fn test_markdown_to_text() {
+    let markdown = "# Title\n\
+                    Returns **the** *first* `item` of [list](https://example.com), \\*not\\* \
+                    a_snake_case.\n\
+                    ```rust\n\
+                    let x = *y;\n\
+                    ```";
+    assert_eq!(
+        markdown_to_text(markdown),
+        "Title\nReturns the first item of list, *not* a_snake_case.\nlet x = *y;"
+    );
+}
+
+/// Split a markdown table row into cells. Escaped pipes don't separate cells.
+fn split_table_row(row: &str) -> Vec<String> {
+    let row = row.trim();
+    let row = row.trim_start_matches('|');
+    let row = if row.ends_with('|') && !row.ends_with("\\|") {
+        &row[..row.len() - 1]
+    } else {
+        row
+    };
+
+    let mut cells = vec![];
+    let mut cell = String::new();
+    let mut chars = row.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                cell.push(c);
+                cell.extend(chars.next());
+            }
+            '|' => cells.push(std::mem::take(&mut cell)),
+            _ => cell.push(c),
+        }
+    }
+    cells.push(cell);
+    cells.iter().map(|cell| cell.trim().to_owned()).collect()
+}
+
+/// Render table rows with columns padded to the same width.
+fn align_table_rows(
+    rows: &[String],
+    inline: &MarkdownInline,
+    footnotes: &mut Vec<String>,
+) -> Vec<String> {
+    #[derive(Clone, Copy)]
+    enum Align {
+        Left,
+        Center,
+        Right,
+    }
+
+    let is_separator = |cells: &[String]| {
+        cells.iter().all(|cell| {
+            let dashes = cell.trim_matches(':');
+            !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+        })
+    };
+
+    let mut aligns = vec![];
+    let mut rows: Vec<Option<Vec<String>>> = rows
+        .iter()
+        .map(|row| {
+            let cells = split_table_row(row);
+            if is_separator(&cells) {
+                aligns = cells
+                    .iter()
+                    .map(|cell| match (cell.starts_with(':'), cell.ends_with(':')) {
+                        (true, true) => Align::Center,
+                        (false, true) => Align::Right,
+                        _ => Align::Left,
+                    }).collect();
+                None
+            } else {
+                Some(
+                    cells
+                        .iter()
+                        .map(|cell| inline.strip(cell, Some(footnotes)))
+                        .collect(),
+                )
+            }
+        }).collect();
+
+    let columns = rows
+        .iter()
+        .flatten()
+        .map(Vec::len)
+        .max()
+        .unwrap_or_default();
+    let mut widths = vec![1; columns];
+    for cells in rows.iter_mut().flatten() {
+        cells.resize(columns, String::new());
+        for (width, cell) in widths.iter_mut().zip(cells.iter()) {
+            *width = std::cmp::max(*width, cell.chars().count());
+        }
+    }
+
+    rows.into_iter()
+        .map(|cells| {
+            let cells: Vec<_> = match cells {
+                Some(cells) => cells
+                    .iter()
+                    .enumerate()
+                    .map(|(i, cell)| {
+                        let padding = widths[i] - cell.chars().count();
+                        let (left, right) = match aligns.get(i).cloned().unwrap_or(Align::Left) {
+                            Align::Left => (0, padding),
+                            Align::Center => (padding / 2, padding - padding / 2),
+                            Align::Right => (padding, 0),
+                        };
+                        format!("{}{}{}", " ".repeat(left), cell, " ".repeat(right))
+                    }).collect(),
+                None => widths.iter().map(|width| "-".repeat(*width)).collect(),
+            };
+            format!("| {} |", cells.join(" | "))
+        }).collect()
+}
+
+/// Render markdown for display: markup is removed, links become footnotes and tables are
+/// aligned. Fenced code blocks are kept verbatim, and returned as regions to be highlighted with
+/// syntax of their language.
+pub fn markdown_to_display(markdown: &str) -> (Vec<String>, Vec<HighlightRegion>) {
+    let inline = &*MARKDOWN_INLINE;
+
+    let mut lines = vec![];
+    let mut regions = vec![];
+    let mut footnotes = vec![];
+    let mut table = vec![];
+    // Fence, language and first line of current code block.
+    let mut code_block: Option<(String, String, usize)> = None;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some((fence, filetype, start)) = code_block.take() {
+            if !trimmed.starts_with(&fence) {
+                lines.push(line.to_owned());
+                code_block = Some((fence, filetype, start));
+            } else if lines.len() > start && !filetype.is_empty() {
+                regions.push(HighlightRegion {
+                    filetype,
+                    start: start as u64,
+                    end: lines.len() as u64 - 1,
+                });
+            }
+            continue;
+        }
+
+        if trimmed.starts_with('|') {
+            table.push(line.to_owned());
+            continue;
+        }
+        if !table.is_empty() {
+            lines.extend(align_table_rows(&table, inline, &mut footnotes));
+            table.clear();
+        }
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            let filetype = trimmed
+                .trim_start_matches(['`', '~'])
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .find(|s| !s.is_empty())
+                .unwrap_or_default();
+            code_block = Some((trimmed[..3].to_owned(), filetype.to_owned(), lines.len()));
+            continue;
+        }
+
+        lines.push(inline.strip(line, Some(&mut footnotes)));
+    }
+
+    if !table.is_empty() {
+        lines.extend(align_table_rows(&table, inline, &mut footnotes));
+    }
+    // Unterminated code block runs till end.
+    if let Some((_, filetype, start)) = code_block {
+        if lines.len() > start && !filetype.is_empty() {
+            regions.push(HighlightRegion {
+                filetype,
+                start: start as u64,
+                end: lines.len() as u64 - 1,
+            });
+        }
+    }
+
+    if !footnotes.is_empty() {
+        lines.push(String::new());
+        for (i, target) in footnotes.iter().enumerate() {
+            lines.push(format!("[{}]: {}", i + 1, target));
+        }
+    }
+
+    (lines, regions)
+}
+
+#[test]
+fn test_markdown_to_display() {
+    let markdown = "See [docs](https://docs.rs) and _[again](https://docs.rs)_.\n\
+                    ```rust\n\
+                    fn f() -> *const u8;\n\
+                    ```\n\
+                    | Name | Value |\n\
+                    |:-----|------:|\n\
+                    | `a\\|b` | **1** |\n\
+                    | long name | 100 |\n\
+                    ~~~\n\
+                    plain\n\
+                    ~~~\n\
+                    ```python\n\
+                    x = 1";
+    let (lines, regions) = markdown_to_display(markdown);
+    assert_eq!(
+        lines,
+        vec![
+            "See docs[1] and again[1].",
+            "fn f() -> *const u8;",
+            "| Name      | Value |",
+            "| --------- | ----- |",
+            "| a|b       |     1 |",
+            "| long name |   100 |",
+            "plain",
+            "x = 1",
+            "",
+            "[1]: https://docs.rs",
+        ]
+    );
+    assert_eq!(
+        regions,
+        vec![
+            HighlightRegion {
+                filetype: "rust".to_owned(),
+                start: 1,
+                end: 1,
+            },
+            HighlightRegion {
+                filetype: "python".to_owned(),
+                start: 7,
+                end: 7,
+            },
+        ]
+    );
+}
+
 pub trait Canonicalize {
     fn canonicalize(&self) -> String;
 }
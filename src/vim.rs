@@ -15,22 +15,60 @@ impl State {
                 Message::Notification(lang_id, notification) => {
                     return Ok(Call::Notification(lang_id, notification));
                 }
-                Message::Output(output) => {
-                    let mid = output.id().to_int()?;
-                    self.pending_outputs.insert(mid, output);
-                }
+                Message::Output(output) => self.store_output(output)?,
             }
         }
     }
 
-    fn poll_output(&mut self, id: Id) -> Fallible<rpc::Output> {
+    /// Keep output for the call waiting for it, unless the call has given up already.
+    fn store_output(&mut self, output: rpc::Output) -> Fallible<()> {
+        let mid = output.id().to_int()?;
+        if self.abandoned_calls.remove(&mid).is_some() {
+            info!("Dropped late response. id: {}", mid);
+        } else {
+            self.pending_outputs.insert(mid, output);
+        }
+        Ok(())
+    }
+
+    /// Stop waiting for response of a call, forgetting calls abandoned long ago.
+    fn abandon_call(&mut self, id: Id) {
+        let now = Instant::now();
+        self.abandoned_calls
+            .retain(|_, since| now.duration_since(*since) < AbandonedCallLifetime);
+        self.abandoned_calls.insert(id, now);
+    }
+
+    fn poll_output(&mut self, id: Id, deadline: Option<Instant>) -> Fallible<rpc::Output> {
         if let Some(output) = self.pending_outputs.remove(&id) {
             return Ok(output);
         }
 
         loop {
-            let msg = self.rx.recv_timeout(self.wait_output_timeout)?;
+            let timeout = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        self.abandon_call(id);
+                        bail!("Timed out waiting for response! id: {}", id);
+                    }
+                    deadline - now
+                }
+                None => self.wait_output_timeout,
+            };
+            let msg = match self.rx.recv_timeout(timeout) {
+                Ok(msg) => msg,
+                Err(err) => {
+                    self.abandon_call(id);
+                    return Err(err.into());
+                }
+            };
             match msg {
+                // A server may need an answer from us before it responds, e.g.,
+                // `workspace/applyEdit` sent while executing a command on save.
+                Message::MethodCall(Some(lang_id), method_call) if deadline.is_some() => {
+                    self.handle_and_answer(Some(&lang_id), method_call)
+                }
                 Message::MethodCall(lang_id, method_call) => self
                     .pending_calls
                     .push_back(Call::MethodCall(lang_id, method_call)),
@@ -38,34 +76,37 @@ impl State {
                     .pending_calls
                     .push_back(Call::Notification(lang_id, notification)),
                 Message::Output(output) => {
-                    let mid = output.id().to_int()?;
-                    if mid == id {
+                    if output.id().to_int()? == id {
                         return Ok(output);
-                    } else {
-                        self.pending_outputs.insert(mid, output);
                     }
+                    self.store_output(output)?;
                 }
             }
         }
     }
 
+    /// Handle a method call and send back its result.
+    fn handle_and_answer(&mut self, languageId: Option<&str>, method_call: rpc::MethodCall) {
+        let result = self.handle_method_call(languageId, &method_call);
+        if let Err(ref err) = result {
+            error!("{:?}", err);
+            if err.find_root_cause().downcast_ref::<LCError>().is_none() {
+                error!(
+                    "Error handling message: {}\n\nMessage: {}\n\nError: {:?}",
+                    err,
+                    serde_json::to_string(&method_call).unwrap_or_default(),
+                    err
+                );
+            }
+        }
+        let _ = self.output(languageId, method_call.id, result);
+    }
+
     pub fn loop_message(&mut self) -> Fallible<()> {
         loop {
             match self.poll_call()? {
                 Call::MethodCall(lang_id, method_call) => {
-                    let result = self.handle_method_call(lang_id.as_deref(), &method_call);
-                    if let Err(ref err) = result {
-                        error!("{:?}", err);
-                        if err.find_root_cause().downcast_ref::<LCError>().is_none() {
-                            error!(
-                                "Error handling message: {}\n\nMessage: {}\n\nError: {:?}",
-                                err,
-                                serde_json::to_string(&method_call).unwrap_or_default(),
-                                err
-                            );
-                        }
-                    }
-                    let _ = self.output(lang_id.as_deref(), method_call.id, result);
+                    self.handle_and_answer(lang_id.as_deref(), method_call)
                 }
                 Call::Notification(lang_id, notification) => {
                     let result = self.handle_notification(lang_id.as_deref(), &notification);
@@ -114,6 +155,21 @@ impl State {
 
     /// RPC method call.
     pub fn call<P, V>(&mut self, languageId: Option<&str>, method: &str, params: P) -> Fallible<V>
+    where
+        P: Serialize,
+        V: DeserializeOwned,
+    {
+        self.call_until(languageId, method, params, None)
+    }
+
+    /// RPC method call, giving up waiting for response once `deadline` passes.
+    pub fn call_until<P, V>(
+        &mut self,
+        languageId: Option<&str>,
+        method: &str,
+        params: P,
+        deadline: Option<Instant>,
+    ) -> Fallible<V>
     where
         P: Serialize,
         V: DeserializeOwned,
@@ -131,7 +187,7 @@ impl State {
         let message = serde_json::to_string(&method_call)?;
         self.write(languageId, &message)?;
 
-        match self.poll_output(id)? {
+        match self.poll_output(id, deadline)? {
             rpc::Output::Success(success) => Ok(serde_json::from_value(success.result)?),
             rpc::Output::Failure(failure) => Err(format_err!("{}", failure.error.message)),
         }
@@ -249,6 +305,36 @@ impl State {
         Ok(())
     }
 
+    /// Replace lines `start..end` (0-based, end exclusive) of current buffer with `replacement`.
+    pub fn set_lines(&mut self, start: usize, end: usize, replacement: &[String]) -> Fallible<()> {
+        if start == end && replacement.is_empty() {
+            return Ok(());
+        }
+
+        if self.is_nvim {
+            self.call::<_, Option<u8>>(
+                None,
+                "nvim_buf_set_lines",
+                json!([0, start, end, false, replacement]),
+            )?;
+            return Ok(());
+        }
+
+        let common = std::cmp::min(end - start, replacement.len());
+        if common > 0 {
+            self.setline(start as u64 + 1, &replacement[..common])?;
+        }
+        if replacement.len() > common {
+            let lnum = start + common;
+            if self.call::<_, u8>(None, "append", json!([lnum, &replacement[common..]]))? != 0 {
+                bail!("Failed to append lines!");
+            }
+        } else if end - start > common {
+            self.command(format!("silent {},{}delete _", start + common + 1, end))?;
+        }
+        Ok(())
+    }
+
     pub fn edit<P: AsRef<Path>>(&mut self, goto_cmd: &Option<String>, path: P) -> Fallible<()> {
         let path = path.as_ref().to_string_lossy();
 
@@ -420,3 +506,56 @@ pub fn loop_reader<T: BufRead>(
 
     Ok(())
 }
+
+#[cfg(test)]
+#[derive(Clone, Debug, Default)]
+struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl SyncWrite for SharedBuffer {}
+
+#[test]
+fn test_poll_output_answers_server_call() {
+    let mut state = State::new().unwrap();
+    let buffer = SharedBuffer::default();
+    state
+        .writers
+        .insert("rust".into(), Box::new(buffer.clone()));
+
+    // Server asks something before responding to our call, like `workspace/applyEdit` during
+    // `workspace/executeCommand`.
+    let method_call = rpc::MethodCall {
+        jsonrpc: Some(rpc::Version::V2),
+        id: rpc::Id::Num(7),
+        method: "$/unknownRequest".into(),
+        params: Params::None,
+    };
+    state
+        .tx
+        .send(Message::MethodCall(Some("rust".into()), method_call))
+        .unwrap();
+    let output = rpc::Output::Success(rpc::Success {
+        jsonrpc: Some(rpc::Version::V2),
+        result: Value::Null,
+        id: rpc::Id::Num(1),
+    });
+    state.tx.send(Message::Output(output.clone())).unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(1);
+    assert_eq!(state.poll_output(1, Some(deadline)).unwrap(), output);
+    assert!(state.pending_calls.is_empty());
+    let written = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    assert!(written.contains(r#""id":7"#));
+}